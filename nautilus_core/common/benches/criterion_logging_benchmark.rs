@@ -0,0 +1,64 @@
+// -------------------------------------------------------------------------------------------------
+//  Copyright (C) 2015-2023 Nautech Systems Pty Ltd. All rights reserved.
+//  https://nautechsystems.io
+//
+//  Licensed under the GNU Lesser General Public License Version 3.0 (the "License");
+//  You may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at https://www.gnu.org/licenses/lgpl-3.0.en.html
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+// -------------------------------------------------------------------------------------------------
+
+use std::{io, time::Duration};
+
+use criterion::{criterion_group, Criterion};
+use nautilus_common::{
+    enums::{LogColor, LogLevel},
+    logging::{LogWriter, Logger, LoggerConfig},
+};
+use nautilus_core::uuid::UUID4;
+use nautilus_model::identifiers::trader_id::TraderId;
+
+pub fn criterion_logging_benchmark(c: &mut Criterion) {
+    let mut logger = Logger::new(
+        TraderId::from("TRADER-001"),
+        String::from("user-01"),
+        UUID4::new(),
+        LogLevel::Info,
+        None,
+        None,
+        None,
+        None,
+        None,
+        false,
+        LoggerConfig {
+            console_writer: Some(LogWriter::new(io::sink())),
+            ..LoggerConfig::default()
+        },
+    );
+
+    c.bench_function("logger_format_1000_lines", |b| {
+        b.iter(|| {
+            let mut token = 0;
+            for _ in 0..1000 {
+                token = logger
+                    .send(
+                        1_650_000_000_000_000,
+                        LogLevel::Info,
+                        LogColor::Normal,
+                        String::from("RiskEngine"),
+                        String::from("Order accepted by venue."),
+                    )
+                    .unwrap();
+            }
+            logger.wait_flushed(token, Duration::from_secs(10));
+        });
+    });
+}
+
+criterion_group!(benches, criterion_logging_benchmark);
+criterion::criterion_main!(benches);
@@ -466,7 +466,7 @@ impl Clock for LiveClock {
 ////////////////////////////////////////////////////////////////////////////////
 // Stubs
 ////////////////////////////////////////////////////////////////////////////////
-#[cfg(test)]
+#[cfg(any(test, feature = "stubs"))]
 pub mod stubs {
     use rstest::fixture;
 
@@ -26,7 +26,7 @@ pub mod timer;
 #[cfg(feature = "ffi")]
 pub mod timer_api;
 
-#[cfg(feature = "test")]
+#[cfg(feature = "stubs")]
 pub mod stubs {
-    use crate::{clock::stubs::*, logging::stubs::*};
+    pub use crate::{clock::stubs::*, logging::stubs::*};
 }
@@ -14,29 +14,85 @@
 // -------------------------------------------------------------------------------------------------
 
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt,
-    fs::{create_dir_all, File},
-    io::{self, BufWriter, Stderr, Stdout, Write},
+    fs::{create_dir_all, File, OpenOptions},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Stderr, Stdout, Write},
     path::{Path, PathBuf},
-    sync::mpsc::{channel, Receiver, SendError, Sender},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{
+            channel, sync_channel, Receiver, RecvTimeoutError, SendError, Sender, SyncSender,
+            TrySendError,
+        },
+        Arc, Condvar, Mutex, MutexGuard, TryLockError,
+    },
     thread,
+    time::{Duration, SystemTime},
+};
+#[cfg(feature = "log-stream")]
+use std::{
+    sync::mpsc::{RecvError, TryRecvError},
+    time::Instant,
 };
 
+use anyhow::bail;
 use chrono::{prelude::*, Utc};
-use nautilus_core::{datetime::unix_nanos_to_iso8601, time::UnixNanos, uuid::UUID4};
-use nautilus_model::identifiers::trader_id::TraderId;
-use serde::{Deserialize, Serialize};
+use nautilus_core::{
+    correctness::check_valid_string,
+    datetime::unix_nanos_to_iso8601,
+    time::{duration_since_unix_epoch, UnixNanos},
+    uuid::UUID4,
+};
+use nautilus_model::identifiers::{
+    client_order_id::ClientOrderId, instrument_id::InstrumentId, position_id::PositionId,
+    trade_id::TradeId, trader_id::TraderId, venue_order_id::VenueOrderId,
+};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::enums::{LogColor, LogLevel};
 
+/// The error returned when sending a log event after [`Logger::shutdown`] has begun.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoggerShuttingDown;
+
+impl fmt::Display for LoggerShuttingDown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Logger is shutting down")
+    }
+}
+
+impl std::error::Error for LoggerShuttingDown {}
+
+/// The maximum time [`Logger::take_captured`] waits for sent events to be handled.
+pub const CAPTURE_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The default component for events logged without one.
+const DEFAULT_COMPONENT: &str = "Logger";
+
+/// The interval at which the logging thread rechecks for sends in flight while shutting down.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The version of the log line format declared by format markers, incremented whenever the
+/// default line template or JSON fields change.
+pub const LOG_FORMAT_VERSION: u32 = 1;
+
 /// Provides a high-performance logger utilizing a MPSC channel under the hood.
 ///
 /// A separate thead is spawned at initialization which receives [`LogEvent`] structs over the
 /// channel.
 pub struct Logger {
-    tx: Sender<LogEvent>,
+    tx: CommandSender,
+    state: Arc<LoggerState>,
+    handle: Option<thread::JoinHandle<()>>,
+    timer_level: LogLevel,
+    level_circular: Option<LogLevel>,
+    default_component: Arc<str>,
+    clock: LogClock,
     /// The trader ID for the logger.
     pub trader_id: TraderId,
     /// The machine ID for the logger.
@@ -64,6 +120,47 @@ pub struct LogEvent {
     component: String,
     /// The log message content.
     message: String,
+    /// The optional caller-supplied key used in place of the message for sampling.
+    #[serde(skip)]
+    sample_key: Option<String>,
+    /// The optional rate domain whose rate limit applies to the event.
+    #[serde(skip)]
+    rate_domain: Option<String>,
+    /// The sequence number assigned when the event was sent.
+    #[serde(skip)]
+    seq: u64,
+    /// The trading identifiers attached to the event, serialized as top-level fields as in JSON
+    /// log lines.
+    #[serde(
+        flatten,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_identifiers"
+    )]
+    identifiers: Option<Box<LogIdentifiers>>,
+}
+
+impl LogEvent {
+    /// Creates a new [`LogEvent`] instance, such as for submitting with [`Logger::log_batch`].
+    #[must_use]
+    pub fn new(
+        timestamp: UnixNanos,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+    ) -> Self {
+        Self {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        }
+    }
 }
 
 impl fmt::Display for LogEvent {
@@ -76,633 +173,7012 @@ impl fmt::Display for LogEvent {
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-impl Logger {
-    pub fn new(
-        trader_id: TraderId,
-        machine_id: String,
-        instance_id: UUID4,
-        level_stdout: LogLevel,
-        level_file: Option<LogLevel>,
-        directory: Option<String>,
-        file_name: Option<String>,
-        file_format: Option<String>,
-        component_levels: Option<HashMap<String, Value>>,
-        is_bypassed: bool,
-    ) -> Self {
-        let (tx, rx) = channel::<LogEvent>();
-        let mut level_filters = HashMap::<String, LogLevel>::new();
+/// Deserializes the flattened identifiers of a log event, with none present mapped to `None`
+/// (rather than to empty identifiers) so a serialized event round trips.
+fn deserialize_identifiers<'de, D>(deserializer: D) -> Result<Option<Box<LogIdentifiers>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let identifiers = LogIdentifiers::deserialize(deserializer)?;
+    Ok((!identifiers.is_empty()).then(|| Box::new(identifiers)))
+}
 
-        if let Some(component_levels_map) = component_levels {
-            for (key, value) in component_levels_map {
-                match serde_json::from_value::<LogLevel>(value) {
-                    Ok(level) => {
-                        level_filters.insert(key, level);
-                    }
-                    Err(e) => {
-                        // Handle the error, e.g. log a warning or ignore the entry
-                        eprintln!("Error parsing log level: {:?}", e);
-                    }
-                }
-            }
-        }
+/// Represents the trading identifiers attached to a log event as structured fields.
+///
+/// Identifiers are rendered with their `Display` representation, then written as separate fields
+/// in JSON and appended to the message as `key=value` pairs in plain text.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogIdentifiers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    venue_order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trade_id: Option<String>,
+}
 
-        let trader_id_clone = trader_id.value.to_string();
-        let instance_id_clone = instance_id.to_string();
+impl LogIdentifiers {
+    /// Creates a new [`LogIdentifiers`] instance with no identifiers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        thread::spawn(move || {
-            Self::handle_messages(
-                &trader_id_clone,
-                &instance_id_clone,
-                level_stdout,
-                level_file,
-                directory,
-                file_name,
-                file_format,
-                level_filters,
-                rx,
-            )
-        });
+    #[must_use]
+    pub fn instrument_id(mut self, instrument_id: InstrumentId) -> Self {
+        self.instrument_id = Some(instrument_id.to_string());
+        self
+    }
 
-        Logger {
-            trader_id,
-            machine_id,
-            instance_id,
-            level_stdout,
-            level_file,
-            is_bypassed,
-            tx,
-        }
+    #[must_use]
+    pub fn client_order_id(mut self, client_order_id: ClientOrderId) -> Self {
+        self.client_order_id = Some(client_order_id.to_string());
+        self
     }
 
-    fn handle_messages(
-        trader_id: &str,
-        instance_id: &str,
-        level_stdout: LogLevel,
-        level_file: Option<LogLevel>,
-        directory: Option<String>,
-        file_name: Option<String>,
-        file_format: Option<String>,
-        level_filters: HashMap<String, LogLevel>,
-        rx: Receiver<LogEvent>,
-    ) {
-        // Setup std I/O buffers
-        let mut out_buf = BufWriter::new(io::stdout());
-        let mut err_buf = BufWriter::new(io::stderr());
+    #[must_use]
+    pub fn venue_order_id(mut self, venue_order_id: VenueOrderId) -> Self {
+        self.venue_order_id = Some(venue_order_id.to_string());
+        self
+    }
 
-        // Setup log file
-        let is_json_format = match file_format.as_ref().map(|s| s.to_lowercase()) {
-            Some(ref format) if format == "json" => true,
-            None => false,
-            Some(ref unrecognized) => {
-                eprintln!(
-                    "Unrecognized log file format: {}. Using plain text format as default.",
-                    unrecognized
-                );
-                false
-            }
-        };
+    #[must_use]
+    pub fn position_id(mut self, position_id: PositionId) -> Self {
+        self.position_id = Some(position_id.to_string());
+        self
+    }
 
-        let file_path = PathBuf::new();
-        let file = if level_file.is_some() {
-            let file_path = Self::create_log_file_path(
-                &directory,
-                &file_name,
-                trader_id,
-                instance_id,
-                is_json_format,
-            );
+    #[must_use]
+    pub fn trade_id(mut self, trade_id: TradeId) -> Self {
+        self.trade_id = Some(trade_id.to_string());
+        self
+    }
 
-            Some(
-                File::options()
-                    .create(true)
-                    .append(true)
-                    .open(file_path)
-                    .expect("Error creating log file"),
-            )
-        } else {
-            None
-        };
+    /// Returns whether no identifiers are attached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields().next().is_none()
+    }
 
-        let mut file_buf = file.map(BufWriter::new);
+    /// Returns the attached identifiers as `(key, value)` pairs in a stable order.
+    fn fields(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("instrument_id", &self.instrument_id),
+            ("client_order_id", &self.client_order_id),
+            ("venue_order_id", &self.venue_order_id),
+            ("position_id", &self.position_id),
+            ("trade_id", &self.trade_id),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_deref().map(|value| (key, value)))
+    }
+}
 
-        // Setup templates for formatting
-        let template_console = String::from(
-            "\x1b[1m{ts}\x1b[0m {color}[{level}] {trader_id}.{component}: {message}\x1b[0m\n",
-        );
-        let template_file = String::from("{ts} [{level}] {trader_id}.{component}: {message}\n");
+/// Represents a "first N then 1-in-M" sampling rule for repetitive log events.
+///
+/// The first `first` events for a sampling key are logged verbatim, after which only every
+/// `every`-th event is logged (an `every` of zero drops all subsequent events).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogSampling {
+    /// The number of initial events to log for each key.
+    pub first: u64,
+    /// The interval at which subsequent events are logged for each key.
+    pub every: u64,
+}
 
-        // Continue to receive and handle log events until channel is hung up
-        while let Ok(event) = rx.recv() {
-            let component_level = level_filters.get(&event.component);
+impl LogSampling {
+    #[must_use]
+    pub fn new(first: u64, every: u64) -> Self {
+        Self { first, every }
+    }
+}
 
-            // Check if the component exists in level_filters and if its level is greater than event.level
-            if let Some(&filter_level) = component_level {
-                if event.level < filter_level {
-                    continue;
-                }
-            }
+/// Represents a token bucket rate limit for log events.
+///
+/// Up to `burst` events are logged at once, with the budget refilled at `per_second` events per
+/// second (events exceeding the budget are handled per the [`RateLimitAction`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The number of events per second the budget is refilled by, which must be positive (a
+    /// limit with any other rate is recorded as the last error and ignored).
+    pub per_second: f64,
+    /// The maximum number of events which can be logged at once.
+    pub burst: u64,
+}
 
-            if event.level >= LogLevel::Error {
-                let line = Self::format_log_line_console(&event, trader_id, &template_console);
-                Self::write_stderr(&mut err_buf, &line);
-                Self::flush_stderr(&mut err_buf);
-            } else if event.level >= level_stdout {
-                let line = Self::format_log_line_console(&event, trader_id, &template_console);
-                Self::write_stdout(&mut out_buf, &line);
-                Self::flush_stdout(&mut out_buf);
-            }
+impl RateLimit {
+    #[must_use]
+    pub fn new(per_second: f64, burst: u64) -> Self {
+        Self { per_second, burst }
+    }
 
-            if let Some(level_file) = level_file {
-                if Self::should_rotate_file(&file_path) {
-                    // Ensure previous file buffer flushed
-                    if let Some(file_buf) = file_buf.as_mut() {
-                        Self::flush_file(file_buf);
-                    };
+    fn is_valid(&self) -> bool {
+        self.per_second > 0.0
+    }
+}
 
-                    let file_path = Self::create_log_file_path(
-                        &directory,
-                        &file_name,
-                        trader_id,
-                        instance_id,
-                        is_json_format,
-                    );
+/// The action taken for log events exceeding a [`RateLimit`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Discard the events, counting them in [`Logger::rate_limited_count`].
+    Drop,
+    /// Discard the events (counting them in [`Logger::rate_limited_count`]), and periodically log
+    /// a warning of how many were suppressed (written when due even if no further events arrive,
+    /// with any remainder written at shutdown).
+    Summarize,
+    /// Wait on the logging thread until the budget allows the event, so no events are lost at
+    /// the cost of delaying all later events (and blocking senders once a bounded channel fills).
+    #[default]
+    Block,
+}
 
-                    let file = File::options()
-                        .create(true)
-                        .append(true)
-                        .open(file_path)
-                        .expect("Error creating log file");
+/// Represents a retention policy for rotated log files.
+///
+/// Rotated files are deleted when they exceed any of the configured limits, with the newest files
+/// retained first. Only files matching the logger's default naming pattern
+/// `{trader_id}_{YYYY-MM-DD}_{instance_id}.{log|json}` are considered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileRetention {
+    /// The maximum age (since last modified) of rotated files to retain.
+    pub max_age: Option<Duration>,
+    /// The maximum number of rotated files to retain.
+    pub max_files: Option<usize>,
+    /// The maximum total size in bytes of rotated files to retain.
+    pub max_total_bytes: Option<u64>,
+}
 
-                    file_buf = Some(BufWriter::new(file));
-                }
+/// Represents the field names used when writing log events in JSON format.
+///
+/// The standard field names are `timestamp`, `level`, `color`, `component` and `message`, any of
+/// which can be renamed to fit a fixed downstream schema (the values are unchanged).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonFieldNames {
+    timestamp: String,
+    level: String,
+    color: String,
+    component: String,
+    message: String,
+}
 
-                if event.level >= level_file {
-                    if let Some(file_buf) = file_buf.as_mut() {
-                        let line = Self::format_log_line_file(
-                            &event,
-                            trader_id,
-                            &template_file,
-                            is_json_format,
-                        );
-                        Self::write_file(file_buf, &line);
-                        Self::flush_file(file_buf);
-                    }
-                }
-            }
+impl Default for JsonFieldNames {
+    fn default() -> Self {
+        Self {
+            timestamp: String::from("timestamp"),
+            level: String::from("level"),
+            color: String::from("color"),
+            component: String::from("component"),
+            message: String::from("message"),
         }
-
-        // Finally ensure remaining buffers are flushed
-        Self::flush_stderr(&mut err_buf);
-        Self::flush_stdout(&mut out_buf);
     }
+}
 
-    fn should_rotate_file(file_path: &Path) -> bool {
-        if file_path.exists() {
-            let current_date_utc = Utc::now().date_naive();
-            let metadata = file_path
-                .metadata()
-                .expect("Failed to read log file metadata");
-            let creation_time = metadata
-                .created()
-                .expect("Failed to get log file creation time");
-
-            let creation_time_utc: DateTime<Utc> = creation_time.into();
-            let creation_date_utc = creation_time_utc.date_naive();
+/// The names of the optional JSON fields, which the standard fields can't be renamed to.
+const RESERVED_JSON_FIELD_NAMES: [&str; 7] = [
+    "event_id",
+    "run_id",
+    "instrument_id",
+    "client_order_id",
+    "venue_order_id",
+    "position_id",
+    "trade_id",
+];
 
-            current_date_utc != creation_date_utc
-        } else {
-            false
+impl JsonFieldNames {
+    /// Creates a new [`JsonFieldNames`] instance from a map of standard field names to their
+    /// renamed target, with any fields not in the map keeping their standard name.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error:
+    /// - If a key in `renames` is not a standard field name.
+    /// - If two fields would share the same target name.
+    /// - If a target name is one of the other JSON fields (the event ID, run ID or identifiers).
+    pub fn new(renames: HashMap<String, String>) -> anyhow::Result<Self> {
+        let mut names = Self::default();
+        for (field, target) in renames {
+            match field.as_str() {
+                "timestamp" => names.timestamp = target,
+                "level" => names.level = target,
+                "color" => names.color = target,
+                "component" => names.component = target,
+                "message" => names.message = target,
+                _ => bail!("Invalid JSON field name to rename, was '{field}'"),
+            }
         }
-    }
-
-    fn default_log_file_basename(trader_id: &str, instance_id: &str) -> String {
-        let current_date_utc = Utc::now().format("%Y-%m-%d");
-        format!("{}_{}_{}", trader_id, current_date_utc, instance_id)
-    }
-
-    fn create_log_file_path(
-        directory: &Option<String>,
-        file_name: &Option<String>,
-        trader_id: &str,
-        instance_id: &str,
-        is_json_format: bool,
-    ) -> PathBuf {
-        let basename = if let Some(file_name) = file_name {
-            file_name.to_owned()
-        } else {
-            Self::default_log_file_basename(trader_id, instance_id)
-        };
-
-        let suffix = if is_json_format { "json" } else { "log" };
-        let mut file_path = PathBuf::new();
 
-        if let Some(directory) = directory {
-            file_path.push(directory);
-            create_dir_all(&file_path).expect("Failed to create directories for log file");
+        let targets = names.as_array();
+        for (i, target) in targets.iter().enumerate() {
+            if targets[..i].contains(target) {
+                bail!("Duplicate JSON field name, was '{target}'");
+            }
+            if RESERVED_JSON_FIELD_NAMES.contains(target) {
+                bail!("JSON field name collides with an optional field, was '{target}'");
+            }
         }
 
-        file_path.push(basename);
-        file_path.set_extension(suffix);
-        file_path
+        Ok(names)
     }
 
-    fn format_log_line_console(event: &LogEvent, trader_id: &str, template: &str) -> String {
-        template
-            .replace("{ts}", &unix_nanos_to_iso8601(event.timestamp))
-            .replace("{color}", &event.color.to_string())
-            .replace("{level}", &event.level.to_string())
-            .replace("{trader_id}", trader_id)
-            .replace("{component}", &event.component)
-            .replace("{message}", &event.message)
+    fn as_array(&self) -> [&str; 5] {
+        [
+            &self.timestamp,
+            &self.level,
+            &self.color,
+            &self.component,
+            &self.message,
+        ]
     }
 
-    fn format_log_line_file(
+    /// Returns the JSON object string for the given `event` using these field names, with the
+    /// timestamp value as given.
+    fn format(
+        &self,
         event: &LogEvent,
-        trader_id: &str,
-        template: &str,
-        is_json_format: bool,
+        timestamp: u64,
+        is_event_id: bool,
+        run_id: Option<&str>,
     ) -> String {
-        if is_json_format {
-            let json_string =
-                serde_json::to_string(event).expect("Error serializing log event to string");
-            format!("{}\n", json_string)
-        } else {
-            template
-                .replace("{ts}", &unix_nanos_to_iso8601(event.timestamp))
-                .replace("{level}", &event.level.to_string())
-                .replace("{trader_id}", trader_id)
-                .replace("{component}", &event.component)
-                .replace("{message}", &event.message)
+        fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+            serde_json::to_string(value).expect("Error serializing log event to string")
         }
-    }
 
-    fn write_stdout(out_buf: &mut BufWriter<Stdout>, line: &str) {
-        match out_buf.write_all(line.as_bytes()) {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error writing to stdout: {e:?}"),
+        let mut json = format!(
+            "{{{}:{},{}:{},{}:{},{}:{},{}:{}",
+            to_json(&self.timestamp),
+            to_json(&timestamp),
+            to_json(&self.level),
+            to_json(&event.level),
+            to_json(&self.color),
+            to_json(&event.color),
+            to_json(&self.component),
+            to_json(&event.component),
+            to_json(&self.message),
+            to_json(&event.message),
+        );
+        if is_event_id {
+            json.push_str(&format!(",\"event_id\":{}", event.seq));
         }
-    }
-
-    fn flush_stdout(out_buf: &mut BufWriter<Stdout>) {
-        match out_buf.flush() {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error flushing stdout: {e:?}"),
+        if let Some(run_id) = run_id {
+            json.push_str(&format!(",\"run_id\":{}", to_json(run_id)));
         }
-    }
-
-    fn write_stderr(err_buf: &mut BufWriter<Stderr>, line: &str) {
-        match err_buf.write_all(line.as_bytes()) {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error writing to stderr: {e:?}"),
+        for (key, value) in event.identifiers.iter().flat_map(|ids| ids.fields()) {
+            json.push_str(&format!(",{}:{}", to_json(key), to_json(value)));
         }
+        json.push('}');
+        json
     }
+}
 
-    fn flush_stderr(err_buf: &mut BufWriter<Stderr>) {
-        match err_buf.flush() {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error flushing stderr: {e:?}"),
-        }
-    }
+/// The format of a log file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogFileFormat {
+    /// Plain text log lines, as for the console (without ANSI colors).
+    #[default]
+    Plain,
+    /// JSON objects, one per line.
+    Json,
+}
 
-    fn write_file(file_buf: &mut BufWriter<File>, line: &str) {
-        match file_buf.write_all(line.as_bytes()) {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error writing to file: {e:?}"),
-        }
-    }
+/// Represents an additional log file written in lockstep with the main log file.
+///
+/// Every event written to the main log file is also written to this file in its own format.
+/// When the main log file is rotated, this file is flushed and writing continues at the `path`
+/// with the new date appended to its file stem, such as `trades_2024-01-02.json` for
+/// `trades.json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFileOutput {
+    /// The path of the log file.
+    pub path: PathBuf,
+    /// The format of the log file.
+    pub format: LogFileFormat,
+    /// If non-ASCII characters are escaped (see [`LoggerConfig::file_ascii_only`]).
+    pub ascii_only: bool,
+}
 
-    fn flush_file(file_buf: &mut BufWriter<File>) {
-        match file_buf.flush() {
-            Ok(_) => {}
-            Err(e) => eprintln!("Error writing to file: {e:?}"),
+impl LogFileOutput {
+    /// Creates a new [`LogFileOutput`] instance.
+    #[must_use]
+    pub fn new(path: PathBuf, format: LogFileFormat) -> Self {
+        Self {
+            path,
+            format,
+            ascii_only: false,
         }
     }
+}
 
-    pub fn send(
-        &mut self,
-        timestamp: u64,
-        level: LogLevel,
-        color: LogColor,
-        component: String,
-        message: String,
-    ) {
-        let event = LogEvent {
-            timestamp,
+/// Represents a circular log file of a fixed size, in which the newest log lines wrap around to
+/// overwrite the oldest, capping disk usage absolutely without rotating files.
+///
+/// The file is pre-allocated to [`CIRCULAR_LOG_HEADER_LEN`] plus `capacity` bytes, with the
+/// header recording the current write offset so that [`read_circular_log_file`] can return the
+/// lines in chronological order. An existing circular file of the same capacity is resumed.
+///
+/// The header is only updated when the writes wrap around and when the file is flushed (at each
+/// [`Logger::checkpoint`] and at shutdown), so lines written since are read once flushed.
+///
+/// The file is held under an exclusive lock while written, so a file already in use by another
+/// logger is reported as an error and not written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircularLogFile {
+    /// The path of the log file.
+    pub path: PathBuf,
+    /// The format of the log file.
+    pub format: LogFileFormat,
+    /// The capacity in bytes of the log lines region of the file.
+    pub capacity: u64,
+    /// The minimum log level to write to the file.
+    pub level: LogLevel,
+    /// If non-ASCII characters are escaped (see [`LoggerConfig::file_ascii_only`]).
+    pub ascii_only: bool,
+}
+
+impl CircularLogFile {
+    /// Creates a new [`CircularLogFile`] instance.
+    #[must_use]
+    pub fn new(path: PathBuf, format: LogFileFormat, capacity: u64, level: LogLevel) -> Self {
+        Self {
+            path,
+            format,
+            capacity,
             level,
-            color,
-            component,
-            message,
-        };
-        if let Err(SendError(e)) = self.tx.send(event) {
-            eprintln!("Error sending log event: {}", e);
+            ascii_only: false,
         }
     }
+}
 
-    pub fn debug(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
-        self.send(timestamp, LogLevel::Debug, color, component, message)
+/// Returns the `text` with each non-ASCII character escaped if `is_ascii_only`.
+///
+/// Plain text is escaped as `\u{XXXX}`, while JSON is escaped as `\uXXXX` (with characters
+/// outside the Basic Multilingual Plane as UTF-16 surrogate pairs) so it remains valid JSON which
+/// decodes to the original text. Non-ASCII characters only occur within JSON strings.
+fn escape_non_ascii(text: &str, is_ascii_only: bool, is_json: bool) -> Cow<'_, str> {
+    if !is_ascii_only || text.is_ascii() {
+        return Cow::Borrowed(text);
     }
-
-    pub fn info(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
-        self.send(timestamp, LogLevel::Info, color, component, message)
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else if is_json {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{unit:04x}"));
+            }
+        } else {
+            escaped.push_str(&format!("\\u{{{:04X}}}", c as u32));
+        }
     }
+    Cow::Owned(escaped)
+}
 
-    pub fn warn(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
-        self.send(timestamp, LogLevel::Warning, color, component, message)
+/// The length in bytes of the header at the start of a [`CircularLogFile`].
+///
+/// The header is the magic bytes `NTCIRLOG`, then the capacity and write offset as little endian
+/// `u64`s, then a byte which is one once the writes have wrapped around, then a byte which is one
+/// if the oldest line at the write offset is whole (padded with zeros).
+pub const CIRCULAR_LOG_HEADER_LEN: u64 = 32;
+
+const CIRCULAR_LOG_MAGIC: &[u8; 8] = b"NTCIRLOG";
+
+/// Reads the log lines of a [`CircularLogFile`] in chronological order.
+///
+/// Once the writes have wrapped around, the oldest line is omitted if it was partly overwritten.
+///
+/// # Errors
+///
+/// This function returns an error if the file can't be read or has no valid circular log header
+/// (including one whose capacity or write offset doesn't fit the file).
+pub fn read_circular_log_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    let bytes = std::fs::read(path)?;
+    let (end, header) = CircularFileSink::parse_header(&bytes)
+        .and_then(|header| {
+            let end = CIRCULAR_LOG_HEADER_LEN.checked_add(header.capacity)?;
+            (bytes.len() as u64 >= end && header.offset <= header.capacity)
+                .then_some((end as usize, header))
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid circular log header"))?;
+
+    let data = &bytes[CIRCULAR_LOG_HEADER_LEN as usize..end];
+    let offset = header.offset as usize;
+    let ordered = if header.is_wrapped {
+        let ordered = [&data[offset..], &data[..offset]].concat();
+        if header.is_line_boundary {
+            ordered
+        } else {
+            match ordered.iter().position(|&b| b == b'\n') {
+                Some(end) => ordered[end + 1..].to_vec(),
+                None => Vec::new(),
+            }
+        }
+    } else {
+        data[..offset].to_vec()
+    };
+    Ok(String::from_utf8_lossy(&ordered)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// Represents the header of a circular log file (see [`CIRCULAR_LOG_HEADER_LEN`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct CircularLogHeader {
+    capacity: u64,
+    offset: u64,
+    is_wrapped: bool,
+    /// If the oldest data at the write offset starts a line, so no line was partly overwritten.
+    is_line_boundary: bool,
+}
+
+/// Provides an open [`CircularLogFile`] written by the logging thread.
+struct CircularFileSink {
+    path: PathBuf,
+    is_json_format: bool,
+    is_ascii_only: bool,
+    level: LogLevel,
+    header: CircularLogHeader,
+    file: Option<File>,
+}
+
+impl CircularFileSink {
+    fn open(output: &CircularLogFile, state: &LoggerState) -> Self {
+        let mut sink = Self {
+            path: output.path.clone(),
+            is_json_format: output.format == LogFileFormat::Json,
+            is_ascii_only: output.ascii_only,
+            level: output.level,
+            header: CircularLogHeader {
+                capacity: output.capacity,
+                ..Default::default()
+            },
+            file: None,
+        };
+        match sink.open_file() {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                state.record_error(format!(
+                    "Error opening circular log file: {} is already in use by another logger",
+                    sink.path.display()
+                ));
+            }
+            Err(e) => {
+                state.record_error(format!(
+                    "Error opening circular log file {}: {e:?}",
+                    sink.path.display()
+                ));
+            }
+        }
+        sink
     }
 
-    pub fn error(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
-        self.send(timestamp, LogLevel::Error, color, component, message)
+    /// Opens the file under an exclusive lock (see [`FileSink`]), resuming from its header if it's
+    /// a circular file of the same capacity, otherwise pre-allocating it as an empty circular
+    /// file.
+    fn open_file(&mut self) -> io::Result<()> {
+        let capacity = self.header.capacity;
+        if capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Circular log file capacity must be positive",
+            ));
+        }
+
+        let mut file = open_locked(
+            &self.path,
+            File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false),
+        )?;
+        let mut header = [0; CIRCULAR_LOG_HEADER_LEN as usize];
+        let resumed = if file.metadata()?.len() == CIRCULAR_LOG_HEADER_LEN + capacity
+            && file.read_exact(&mut header).is_ok()
+        {
+            Self::parse_header(&header)
+                .filter(|header| header.capacity == capacity && header.offset < capacity)
+        } else {
+            None
+        };
+
+        match resumed {
+            Some(header) => self.header = header,
+            None => {
+                file.set_len(0)?;
+                file.set_len(CIRCULAR_LOG_HEADER_LEN + capacity)?;
+                self.header = CircularLogHeader {
+                    capacity,
+                    ..Default::default()
+                };
+                Self::write_header(&mut file, &self.header)?;
+            }
+        }
+        self.file = Some(file);
+        Ok(())
     }
 
-    pub fn critical(
+    fn parse_header(bytes: &[u8]) -> Option<CircularLogHeader> {
+        if bytes.len() < CIRCULAR_LOG_HEADER_LEN as usize || &bytes[..8] != CIRCULAR_LOG_MAGIC {
+            return None;
+        }
+        Some(CircularLogHeader {
+            capacity: u64::from_le_bytes(bytes[8..16].try_into().ok()?),
+            offset: u64::from_le_bytes(bytes[16..24].try_into().ok()?),
+            is_wrapped: bytes[24] == 1,
+            is_line_boundary: bytes[25] == 1,
+        })
+    }
+
+    fn write_header(file: &mut File, header: &CircularLogHeader) -> io::Result<()> {
+        let mut bytes = [0; CIRCULAR_LOG_HEADER_LEN as usize];
+        bytes[..8].copy_from_slice(CIRCULAR_LOG_MAGIC);
+        bytes[8..16].copy_from_slice(&header.capacity.to_le_bytes());
+        bytes[16..24].copy_from_slice(&header.offset.to_le_bytes());
+        bytes[24] = u8::from(header.is_wrapped);
+        bytes[25] = u8::from(header.is_line_boundary);
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&bytes)
+    }
+
+    /// Writes the header for the lines written so far, so they can be read.
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => Self::write_header(file, &self.header),
+            None => Ok(()),
+        }
+    }
+
+    fn write_line(&mut self, line: &str, state: &LoggerState) {
+        let line = escape_non_ascii(line, self.is_ascii_only, self.is_json_format);
+        if let Err(e) = self.try_write_line(line.as_bytes()) {
+            state.record_error(format!("Error writing to circular log file: {e:?}"));
+        }
+    }
+
+    fn try_write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let header = &mut self.header;
+        if line.is_empty() {
+            return Ok(());
+        }
+        // Only the end of a line longer than the whole capacity can be kept
+        let is_truncated = line.len() as u64 > header.capacity;
+        let line = &line[line.len().saturating_sub(header.capacity as usize)..];
+        let split = line.len().min((header.capacity - header.offset) as usize);
+        let (head, tail) = line.split_at(split);
+        let is_wrapping = header.offset + head.len() as u64 == header.capacity;
+
+        // The oldest data left at the new offset starts a line if the last byte overwritten ended
+        // one (or if it's the start of the file, as the writes first wrap at a line boundary)
+        if header.is_wrapped || is_wrapping {
+            let last = match tail.len() {
+                0 => header.offset + head.len() as u64 - 1,
+                len => len as u64 - 1,
+            };
+            header.is_line_boundary = if line.len() as u64 == header.capacity {
+                // The line overwrites all the data, so it's the oldest
+                !is_truncated
+            } else if !header.is_wrapped && tail.is_empty() {
+                true
+            } else {
+                let mut byte = [0; 1];
+                file.seek(SeekFrom::Start(CIRCULAR_LOG_HEADER_LEN + last))?;
+                file.read_exact(&mut byte)?;
+                byte[0] == b'\n'
+            };
+        }
+
+        file.seek(SeekFrom::Start(CIRCULAR_LOG_HEADER_LEN + header.offset))?;
+        file.write_all(head)?;
+        header.offset += head.len() as u64;
+        if is_wrapping {
+            file.seek(SeekFrom::Start(CIRCULAR_LOG_HEADER_LEN))?;
+            file.write_all(tail)?;
+            header.offset = tail.len() as u64;
+            header.is_wrapped = true;
+            return Self::write_header(file, header);
+        }
+        Ok(())
+    }
+}
+
+/// The behavior when a log file is already in use by another logger (in this or another process).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogFileConflict {
+    /// Writes to a uniquely suffixed path instead, such as `trading_1.log` for `trading.log`.
+    #[default]
+    Suffix,
+    /// Reports an error and does not write to the file.
+    Error,
+}
+
+/// The maximum suffix tried for a log file path before giving up with [`LogFileConflict::Suffix`].
+const MAX_LOG_FILE_SUFFIX: usize = 100;
+
+/// Provides an open log file written by the logging thread.
+///
+/// The file is held under an exclusive advisory lock so that no other logger can interleave
+/// writes to the same file.
+struct FileSink {
+    path: PathBuf,
+    is_json_format: bool,
+    is_ascii_only: bool,
+    /// If the file was empty when opened, so the format marker and header belong at its top.
+    is_new: bool,
+    buf: Option<BufWriter<File>>,
+}
+
+impl FileSink {
+    fn open(
+        path: PathBuf,
+        is_json_format: bool,
+        is_ascii_only: bool,
+        conflict: LogFileConflict,
+        state: &LoggerState,
+    ) -> Self {
+        let mut candidate = path.clone();
+        for suffix in 1..=MAX_LOG_FILE_SUFFIX + 1 {
+            match open_locked(&candidate, File::options().create(true).append(true)) {
+                Ok(file) => {
+                    return Self {
+                        path: candidate,
+                        is_json_format,
+                        is_ascii_only,
+                        is_new: file.metadata().is_ok_and(|m| m.len() == 0),
+                        buf: Some(BufWriter::new(file)),
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if conflict == LogFileConflict::Error {
+                        break;
+                    }
+                    candidate = Self::suffixed_path(&path, suffix);
+                }
+                Err(e) => {
+                    state.record_error(format!(
+                        "Error creating log file {}: {e:?}",
+                        candidate.display()
+                    ));
+                    return Self::closed(candidate, is_json_format, is_ascii_only);
+                }
+            }
+        }
+
+        state.record_error(format!(
+            "Error creating log file: {} is already in use by another logger",
+            path.display()
+        ));
+        Self::closed(path, is_json_format, is_ascii_only)
+    }
+
+    /// Returns a sink for the file at `path` which could not be opened, so nothing is written.
+    fn closed(path: PathBuf, is_json_format: bool, is_ascii_only: bool) -> Self {
+        Self {
+            path,
+            is_json_format,
+            is_ascii_only,
+            is_new: false,
+            buf: None,
+        }
+    }
+
+    /// Closes the current file (releasing its lock) and opens the file at `path`.
+    fn reopen(&mut self, path: PathBuf, conflict: LogFileConflict, state: &LoggerState) {
+        self.buf = None;
+        *self = Self::open(
+            path,
+            self.is_json_format,
+            self.is_ascii_only,
+            conflict,
+            state,
+        );
+    }
+
+    /// Returns the barrier record written at flush points when [`LoggerConfig::flush_barrier`]
+    /// is enabled.
+    fn barrier(is_json_format: bool) -> &'static str {
+        if is_json_format {
+            "{\"_barrier\":true}\n"
+        } else {
+            "# barrier\n"
+        }
+    }
+
+    /// Writes a format marker declaring the [`LOG_FORMAT_VERSION`] (and the line `template` for
+    /// plain text, or the `field_names` for JSON) as the first line of the file, if it's newly
+    /// created.
+    fn write_format_marker(
         &mut self,
-        timestamp: u64,
-        color: LogColor,
-        component: String,
-        message: String,
+        template: &LogTemplate,
+        field_names: &JsonFieldNames,
+        state: &LoggerState,
     ) {
-        self.send(timestamp, LogLevel::Critical, color, component, message)
+        let Some(buf) = self.buf.as_mut().filter(|_| self.is_new) else {
+            return;
+        };
+
+        let text = if self.is_json_format {
+            let fields: Vec<String> = JsonFieldNames::default()
+                .as_array()
+                .iter()
+                .zip(field_names.as_array())
+                .map(|(field, name)| {
+                    format!(
+                        "\"{field}\":{}",
+                        serde_json::to_string(name)
+                            .expect("Error serializing log file format marker")
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"format\":{{\"version\":{LOG_FORMAT_VERSION},\"fields\":{{{}}}}}}}\n",
+                fields.join(",")
+            )
+        } else {
+            let template = serde_json::to_string(&template.source)
+                .expect("Error serializing log file format marker");
+            format!("# format version={LOG_FORMAT_VERSION} template={template}\n")
+        };
+        let text = escape_non_ascii(&text, self.is_ascii_only, self.is_json_format);
+        Logger::write_file(buf, &text, state);
+        Logger::flush_file(buf, state);
+    }
+
+    /// Writes the `header` key-values at the top of the file, if it's newly created (comment
+    /// lines for plain text, or a single header record for JSON).
+    fn write_header(&mut self, header: &[(String, String)], state: &LoggerState) {
+        let Some(buf) = self.buf.as_mut().filter(|_| self.is_new) else {
+            return;
+        };
+        if header.is_empty() {
+            return;
+        }
+
+        let text = if self.is_json_format {
+            let fields: Vec<String> = header
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}:{}",
+                        serde_json::to_string(key).expect("Error serializing log file header"),
+                        serde_json::to_string(value).expect("Error serializing log file header"),
+                    )
+                })
+                .collect();
+            format!("{{\"header\":{{{}}}}}\n", fields.join(","))
+        } else {
+            header
+                .iter()
+                .map(|(key, value)| format!("# {key}: {value}\n"))
+                .collect()
+        };
+        let text = escape_non_ascii(&text, self.is_ascii_only, self.is_json_format);
+        Logger::write_file(buf, &text, state);
+        Logger::flush_file(buf, state);
+    }
+
+    /// Writes a marker line delimiting the start of a run within the file, containing the
+    /// process ID, `start` time and `instance_id`.
+    fn write_run_marker(&mut self, instance_id: &str, start: UnixNanos, state: &LoggerState) {
+        let Some(buf) = self.buf.as_mut() else {
+            return;
+        };
+
+        let pid = std::process::id();
+        let start = unix_nanos_to_iso8601(start);
+        let text = if self.is_json_format {
+            format!(
+                "{{\"run\":{{\"pid\":{pid},\"start\":\"{start}\",\"instance_id\":\"{instance_id}\"}}}}\n"
+            )
+        } else {
+            format!("# run pid={pid} start={start} instance_id={instance_id}\n")
+        };
+        let text = escape_non_ascii(&text, self.is_ascii_only, self.is_json_format);
+        Logger::write_file(buf, &text, state);
+        Logger::flush_file(buf, state);
+    }
+
+    fn suffixed_path(path: &Path, suffix: usize) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut file_name = format!("{stem}_{suffix}");
+        if let Some(extension) = path.extension() {
+            file_name.push('.');
+            file_name.push_str(&extension.to_string_lossy());
+        }
+        path.with_file_name(file_name)
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////
-// Stubs
-////////////////////////////////////////////////////////////////////////////////
-#[cfg(test)]
-pub mod stubs {
-    use nautilus_core::uuid::UUID4;
-    use nautilus_model::identifiers::trader_id::TraderId;
-    use rstest::fixture;
+/// Opens the file at `path` with the `options` under an exclusive advisory lock, returning an
+/// error of kind [`io::ErrorKind::WouldBlock`] if the lock is held elsewhere.
+#[cfg(unix)]
+fn open_locked(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    use std::os::unix::io::AsRawFd;
 
-    use crate::{enums::LogLevel, logging::Logger};
+    let file = options.open(path)?;
+    // Safety: the file descriptor is valid for the lifetime of `file`
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
 
-    #[fixture]
-    pub fn logger() -> Logger {
-        Logger::new(
-            TraderId::from("TRADER-001"),
-            String::from("user-01"),
-            UUID4::new(),
-            LogLevel::Info,
-            None,
-            None,
-            None,
-            None,
-            None,
-            false,
-        )
+/// Opens the file at `path` with the `options` while denying write access to other handles,
+/// returning an error of kind [`io::ErrorKind::WouldBlock`] if it's already open for writing.
+#[cfg(windows)]
+fn open_locked(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_SHARE_READ: u32 = 0x1;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    options
+        .share_mode(FILE_SHARE_READ)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(ERROR_SHARING_VIOLATION) => io::Error::new(io::ErrorKind::WouldBlock, e),
+            _ => e,
+        })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn open_locked(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    options.open(path)
+}
+
+/// Returns whether the file at `path` is held open by a logger (in this or another process).
+fn is_log_file_in_use(path: &Path) -> bool {
+    open_locked(path, File::options().append(true))
+        .is_err_and(|e| e.kind() == io::ErrorKind::WouldBlock)
+}
+
+/// The segments of a console log line which are colored with the log event color.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConsoleColorScope {
+    /// The level, trader ID, component and message are colored.
+    #[default]
+    WholeLine,
+    /// Only the level is colored.
+    LevelOnly,
+    /// Only the message is colored.
+    MessageOnly,
+    /// Nothing is colored.
+    None,
+}
+
+/// Represents a writer which console log lines are written to in place of stdout and stderr.
+///
+/// This allows the console output to be captured or redirected, such as to simulate a slow sink
+/// in tests.
+#[derive(Clone)]
+pub struct LogWriter(Arc<Mutex<dyn Write + Send>>);
+
+impl LogWriter {
+    #[must_use]
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+
+    fn write_line(&self, line: &str, state: &LoggerState) {
+        let mut writer = lock(&self.0);
+        if let Err(e) = writer
+            .write_all(line.as_bytes())
+            .and_then(|_| writer.flush())
+        {
+            state.record_error(format!("Error writing to console writer: {e:?}"));
+        }
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////
-// Tests
-////////////////////////////////////////////////////////////////////////////////
-#[cfg(test)]
-mod tests {
-    use std::time::Duration;
+impl fmt::Debug for LogWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(LogWriter))
+            .finish_non_exhaustive()
+    }
+}
 
-    use nautilus_core::uuid::UUID4;
-    use nautilus_model::identifiers::trader_id::TraderId;
-    use rstest::*;
-    use tempfile::tempdir;
+/// Represents a custom function formatting the timestamps of plain text log lines, which is
+/// called on the logging thread.
+#[derive(Clone)]
+pub struct TimestampFormatter(Arc<dyn Fn(UnixNanos) -> String + Send + Sync>);
 
-    use super::{stubs::*, *};
-    use crate::testing::wait_until;
+impl TimestampFormatter {
+    #[must_use]
+    pub fn new<F: Fn(UnixNanos) -> String + Send + Sync + 'static>(format: F) -> Self {
+        Self(Arc::new(format))
+    }
+}
 
-    #[rstest]
-    fn log_message_serialization() {
-        let log_message = LogEvent {
-            timestamp: 1_000_000_000,
-            level: LogLevel::Info,
-            color: LogColor::Normal,
-            component: "Portfolio".to_string(),
-            message: "This is a log message".to_string(),
+impl fmt::Debug for TimestampFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(TimestampFormatter))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Represents a clock function returning the current UNIX nanoseconds, which can be injected
+/// for testing.
+#[derive(Clone)]
+pub struct LogClock(Arc<dyn Fn() -> UnixNanos + Send + Sync>);
+
+impl LogClock {
+    #[must_use]
+    pub fn new<F: Fn() -> UnixNanos + Send + Sync + 'static>(clock: F) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    /// Creates a new [`LogClock`] instance reading the system clock.
+    #[must_use]
+    pub fn system() -> Self {
+        Self::new(unix_nanos_now)
+    }
+
+    fn now(&self) -> UnixNanos {
+        (self.0)()
+    }
+}
+
+impl Default for LogClock {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl fmt::Debug for LogClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(LogClock)).finish_non_exhaustive()
+    }
+}
+
+/// Represents padding of the level token in plain text log lines to a fixed width, so that the
+/// messages of all levels line up in one column.
+///
+/// The padded level is written as its full name (`DEBUG`, `INFO`, `WARNING`, `ERROR` or
+/// `CRITICAL`), so a `width` of 8 aligns all levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelPadding {
+    /// The minimum width of the level token in characters.
+    pub width: usize,
+    /// The character the level token is padded with (after the level).
+    pub fill: char,
+}
+
+impl LevelPadding {
+    /// Creates a new [`LevelPadding`] instance padding to `width` with spaces.
+    #[must_use]
+    pub fn new(width: usize) -> Self {
+        Self { width, fill: ' ' }
+    }
+
+    fn pad(&self, level: LogLevel) -> String {
+        let mut token = String::from(match level {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warning => "WARNING",
+            LogLevel::Error => "ERROR",
+            LogLevel::Critical => "CRITICAL",
+        });
+        let len = token.chars().count();
+        for _ in len..self.width {
+            token.push(self.fill);
+        }
+        token
+    }
+}
+
+/// The escaping applied to the message of plain text log lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MessageEscaping {
+    /// Messages are written as is.
+    #[default]
+    None,
+    /// Control characters (such as newlines and tabs) are escaped as `\xNN`.
+    ControlChars,
+    /// Control characters are escaped as `\xNN`, and quotes and backslashes with a backslash.
+    Full,
+}
+
+impl MessageEscaping {
+    fn escape(self, message: &str) -> Cow<'_, str> {
+        let is_escaped = |c: char| match self {
+            Self::None => false,
+            Self::ControlChars => c.is_control(),
+            Self::Full => c.is_control() || matches!(c, '"' | '\'' | '\\'),
         };
+        if !message.chars().any(is_escaped) {
+            return Cow::Borrowed(message);
+        }
 
-        let serialized_json = serde_json::to_string(&log_message).unwrap();
-        let deserialized_value: Value = serde_json::from_str(&serialized_json).unwrap();
+        let mut escaped = String::with_capacity(message.len() + 8);
+        for c in message.chars() {
+            if !is_escaped(c) {
+                escaped.push(c);
+            } else if c.is_control() {
+                // Control characters are all within the first 256 code points
+                escaped.push_str(&format!("\\x{:02x}", c as u32));
+            } else {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+        }
+        Cow::Owned(escaped)
+    }
+}
 
-        assert_eq!(deserialized_value["timestamp"], 1_000_000_000);
-        assert_eq!(deserialized_value["level"], "INFO");
-        assert_eq!(deserialized_value["component"], "Portfolio");
-        assert_eq!(deserialized_value["message"], "This is a log message");
+/// The routing of console log lines between stdout and stderr.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConsoleStreamPolicy {
+    /// Error and critical lines are written to stderr, all other lines to stdout.
+    #[default]
+    Split,
+    /// All lines (including errors) are written only to stdout, preserving their relative
+    /// ordering when both streams are merged into one view.
+    SingleStdout,
+}
+
+impl ConsoleStreamPolicy {
+    fn is_stderr(self, level: LogLevel) -> bool {
+        match self {
+            Self::Split => level >= LogLevel::Error,
+            Self::SingleStdout => false,
+        }
+    }
+}
+
+/// Represents whether ANSI colors render on each of the console streams.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct ConsoleColors {
+    stdout: bool,
+    stderr: bool,
+}
+
+impl ConsoleColors {
+    /// Returns whether a console line at the `level` is written with colors, as its stream
+    /// (routed per the `policy`) renders them.
+    fn is_colored(self, level: LogLevel, policy: ConsoleStreamPolicy) -> bool {
+        if policy.is_stderr(level) {
+            self.stderr
+        } else {
+            self.stdout
+        }
+    }
+}
+
+/// The handling of log events with an empty message.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EmptyMessagePolicy {
+    /// Log the event as is, with nothing after the component.
+    #[default]
+    Keep,
+    /// Discard the event (it's filtered out, rather than counted as dropped).
+    Drop,
+    /// Log the event with the message replaced by `<empty>`.
+    Placeholder,
+}
+
+/// The style of timestamps in log lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogTimestampFormat {
+    /// ISO 8601 (RFC 3339) strings with nanosecond precision, such as
+    /// `1970-01-20T02:20:00.000000000Z` (JSON timestamps remain integer UNIX nanoseconds).
+    #[default]
+    Iso8601,
+    /// ISO 8601 (RFC 3339) strings with the given number of fractional second digits (from zero
+    /// up to nine, truncated), such as `1970-01-20T02:20:00.000Z` for three digits.
+    Iso8601Digits(u8),
+    /// Integer UNIX nanoseconds.
+    UnixNanos,
+    /// Integer UNIX microseconds (truncated).
+    UnixMicros,
+    /// Integer UNIX milliseconds (truncated).
+    UnixMillis,
+}
+
+impl LogTimestampFormat {
+    /// Returns the integer UNIX `timestamp` in the unit for this format (nanoseconds for
+    /// [`LogTimestampFormat::Iso8601`]).
+    fn to_unix(self, timestamp: UnixNanos) -> u64 {
+        match self {
+            Self::Iso8601 | Self::Iso8601Digits(_) | Self::UnixNanos => timestamp,
+            Self::UnixMicros => timestamp / 1_000,
+            Self::UnixMillis => timestamp / 1_000_000,
+        }
+    }
+
+    fn format(self, timestamp: UnixNanos) -> String {
+        match self {
+            Self::Iso8601 => unix_nanos_to_iso8601(timestamp),
+            Self::Iso8601Digits(digits) => {
+                let iso = unix_nanos_to_iso8601(timestamp);
+                let Some(point) = iso.find('.') else {
+                    return iso;
+                };
+                // The fraction always has nine digits followed by the `Z` suffix
+                match usize::from(digits.min(9)) {
+                    0 => format!("{}Z", &iso[..point]),
+                    digits => format!("{}Z", &iso[..=point + digits]),
+                }
+            }
+            _ => self.to_unix(timestamp).to_string(),
+        }
+    }
+}
+
+/// Represents a rule which remaps the level of matching log events before they are routed.
+///
+/// An event matches if it originated from `component`, its message contains `pattern` and (if
+/// specified) its level is `from`. This allows known benign messages from a component to be
+/// downgraded (or important ones upgraded).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLevelRemap {
+    /// The component the event must originate from.
+    pub component: String,
+    /// The substring the event message must contain.
+    pub pattern: String,
+    /// The level the event must have, or any level if `None`.
+    pub from: Option<LogLevel>,
+    /// The level to remap the event to.
+    pub to: LogLevel,
+}
+
+impl LogLevelRemap {
+    /// Creates a new [`LogLevelRemap`] instance.
+    #[must_use]
+    pub fn new(component: String, pattern: String, from: Option<LogLevel>, to: LogLevel) -> Self {
+        Self {
+            component,
+            pattern,
+            from,
+            to,
+        }
+    }
+
+    fn matches(&self, event: &LogEvent) -> bool {
+        event.component == self.component
+            && (self.from.is_none() || self.from == Some(event.level))
+            && event.message.contains(&self.pattern)
+    }
+}
+
+/// Provides additional configuration for a [`Logger`].
+#[derive(Clone, Debug)]
+pub struct LoggerConfig {
+    /// The sampling rules for repetitive events, keyed by component.
+    pub sampling: HashMap<String, LogSampling>,
+    /// The sampling rule for components without a specific rule.
+    pub sampling_default: Option<LogSampling>,
+    /// The maximum number of sampling keys tracked before the oldest are evicted.
+    pub sampling_max_keys: usize,
+    /// The rate limits for events sent with a rate domain, keyed by domain.
+    pub rate_limits: HashMap<String, RateLimit>,
+    /// The rate limit for events without a domain (or with a domain without a specific limit).
+    pub rate_limit_default: Option<RateLimit>,
+    /// The action taken for events exceeding their rate limit.
+    pub rate_limit_action: RateLimitAction,
+    /// The minimum interval between rate limit summaries, for [`RateLimitAction::Summarize`].
+    pub rate_limit_summary_interval: Duration,
+    /// The capacity of the in-memory ring buffer of recently logged events (zero to disable).
+    pub buffer_capacity: usize,
+    /// The glyphs to prefix log lines with for each level via the `{glyph}` placeholder.
+    pub glyphs: HashMap<LogLevel, String>,
+    /// The retention policy for rotated log files (only applies to default file naming).
+    pub file_retention: Option<FileRetention>,
+    /// The field names for log files in JSON format.
+    pub json_field_names: JsonFieldNames,
+    /// If logged events are captured in memory for retrieval with [`Logger::take_captured`]
+    /// (see [`Logger::set_capturing`], as the capture buffer is unbounded).
+    pub capture: bool,
+    /// The separator between the trader ID and component in plain text log lines.
+    pub component_separator: String,
+    /// The level at which [`LogTimer`] elapsed durations are logged.
+    pub timer_level: LogLevel,
+    /// The additional files to write alongside the main log file (when file logging is enabled).
+    pub file_outputs: Vec<LogFileOutput>,
+    /// If non-ASCII characters in main log file lines are escaped so that every byte written is
+    /// ASCII (see [`LogFileOutput::ascii_only`] for additional files).
+    ///
+    /// Plain text lines escape each character as `\u{XXXX}`, while JSON lines escape it as
+    /// `\uXXXX` (as a UTF-16 surrogate pair outside the Basic Multilingual Plane) so they remain
+    /// valid JSON which decodes to the original text.
+    pub file_ascii_only: bool,
+    /// If non-ASCII characters in console lines are escaped (see
+    /// [`LoggerConfig::file_ascii_only`]).
+    pub console_ascii_only: bool,
+    /// If non-ASCII characters in the JSON published to log stream subscribers (with the
+    /// `log-stream` feature) are escaped (see [`LoggerConfig::file_ascii_only`]).
+    pub subscriber_ascii_only: bool,
+    /// The optional fixed tag prepended to each plain text console line (but not to log files),
+    /// such as to distinguish the console output of several loggers sharing stdout.
+    pub console_prefix: Option<String>,
+    /// The clock for the current time, read by [`LogTimer`] durations, rate limits, the
+    /// time-to-live of events received from a log stream (with the `log-stream` feature) and the
+    /// logging thread (the system clock by default).
+    pub clock: LogClock,
+    /// The fixed size circular log files to write (independently of file logging), each at its
+    /// own minimum level.
+    pub circular_files: Vec<CircularLogFile>,
+    /// The behavior when a log file is already in use by another logger.
+    pub file_conflict: LogFileConflict,
+    /// The segments of console log lines which are colored.
+    pub console_color_scope: ConsoleColorScope,
+    /// The style of timestamps in log lines.
+    pub timestamp_format: LogTimestampFormat,
+    /// The maximum number of events held while paused (further events are dropped).
+    pub pause_capacity: usize,
+    /// The component for events logged without one, such as with [`Logger::info_default`].
+    ///
+    /// An invalid component (empty, all whitespace or non-ASCII) is recorded as the last error
+    /// and replaced by the default.
+    pub default_component: Arc<str>,
+    /// The rules remapping the level of matching events (the first matching rule applies).
+    pub level_remaps: Vec<LogLevelRemap>,
+    /// The capacity of the channel to the logging thread, or unbounded if `None`.
+    ///
+    /// When the bounded channel is full, sending blocks until there is space, whereas
+    /// [`Logger::try_log`] drops the event.
+    pub channel_capacity: Option<usize>,
+    /// The routing of console log lines between stdout and stderr.
+    pub console_stream_policy: ConsoleStreamPolicy,
+    /// The handling of log events with an empty message.
+    pub empty_message_policy: EmptyMessagePolicy,
+    /// The key-values describing the run, written as a header at the top of each new log file
+    /// (including after rotation) so that every file is independently interpretable.
+    pub file_header: Vec<(String, String)>,
+    /// If a summary block of the run (duration, drops and counts per level and component) is
+    /// written as INFO lines from the default component when the logger shuts down.
+    ///
+    /// The summary is written to every open log file whatever its level, bypassing component
+    /// filters, level remaps, sampling and rate limits, and to the console if it logs INFO.
+    pub shutdown_summary: bool,
+    /// The escaping applied to the message of plain text (console and file) log lines.
+    pub message_escaping: MessageEscaping,
+    /// The writer which console log lines are written to in place of stdout and stderr.
+    pub console_writer: Option<LogWriter>,
+    /// If a marker line with the process ID, start time and instance ID is written to each log
+    /// file when opened (including after rotation), delimiting runs appended to the same file.
+    pub run_marker: bool,
+    /// If a format marker line declaring the [`LOG_FORMAT_VERSION`] and the exact line template
+    /// (or the JSON field names for JSON files) is written as the first line of each new log file
+    /// (including after rotation), so parsers can detect the format per file.
+    pub format_marker: bool,
+    /// If a barrier record is written to log files at each flush (a `{"_barrier":true}` line for
+    /// JSON, or a `# barrier` comment line for plain text), so downstream consumers can commit
+    /// batches at consistent points.
+    ///
+    /// Log files are flushed after each written event, so each event is followed by a barrier.
+    pub flush_barrier: bool,
+    /// If log files are flushed only by [`Logger::checkpoint`] (besides when their buffers fill,
+    /// at rotation and at shutdown), rather than after each event.
+    ///
+    /// Barriers enabled with `flush_barrier` are then written at each checkpoint, and events
+    /// written to log files are only flushed for [`Logger::wait_flushed`] once the log files are
+    /// next flushed (other than by a full buffer).
+    pub flush_on_checkpoint: bool,
+    /// The padding of the level token in plain text (console and file) log lines, if any, which
+    /// writes each level by its full name.
+    pub level_padding: Option<LevelPadding>,
+    /// The custom function formatting timestamps of plain text log lines, overriding the
+    /// built-in `timestamp_format`.
+    pub timestamp_formatter: Option<TimestampFormatter>,
+    /// The minimum free disk space in bytes for writing log files, below which file writes are
+    /// paused (with a warning on the console) until the free space recovers.
+    pub disk_free_floor: Option<u64>,
+    /// The interval at which free disk space is checked against the `disk_free_floor`.
+    pub disk_check_interval: Duration,
+    /// If console log lines are written as JSON objects (one per line, without ANSI colors),
+    /// such as for containerized deployments scraping stdout.
+    pub console_json: bool,
+    /// If log lines include the event ID, a run-local counter assigned when each event is sent
+    /// (giving a total order of events even when timestamps are identical).
+    ///
+    /// The ID is written after the timestamp of plain text lines, and as an `event_id` field of
+    /// JSON objects.
+    pub event_ids: bool,
+    /// The optional run ID for correlating log output with a backtest run, such as an
+    /// iteration of a parameter sweep.
+    ///
+    /// The ID is included in the main log file name, written as `run_id=...` after the timestamp
+    /// of plain text lines (and available as a `{run_id}` template placeholder), and as a
+    /// `run_id` field of JSON objects.
+    ///
+    /// Path separators (`/` and `\`) and `..` are replaced with `_` so the ID can't move the log
+    /// file out of its directory, and an otherwise invalid ID (empty, all whitespace or
+    /// non-ASCII) is recorded as the last error and ignored.
+    pub run_id: Option<String>,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            sampling: HashMap::new(),
+            sampling_default: None,
+            sampling_max_keys: 10_000,
+            rate_limits: HashMap::new(),
+            rate_limit_default: None,
+            rate_limit_action: RateLimitAction::default(),
+            rate_limit_summary_interval: Duration::from_secs(10),
+            buffer_capacity: 0,
+            glyphs: HashMap::new(),
+            file_retention: None,
+            json_field_names: JsonFieldNames::default(),
+            capture: false,
+            component_separator: String::from("."),
+            timer_level: LogLevel::Debug,
+            file_outputs: Vec::new(),
+            file_ascii_only: false,
+            console_ascii_only: false,
+            subscriber_ascii_only: false,
+            console_prefix: None,
+            clock: LogClock::system(),
+            circular_files: Vec::new(),
+            file_conflict: LogFileConflict::default(),
+            console_color_scope: ConsoleColorScope::default(),
+            timestamp_format: LogTimestampFormat::default(),
+            pause_capacity: 100_000,
+            default_component: Arc::from(DEFAULT_COMPONENT),
+            level_remaps: Vec::new(),
+            channel_capacity: None,
+            console_stream_policy: ConsoleStreamPolicy::default(),
+            empty_message_policy: EmptyMessagePolicy::default(),
+            file_header: Vec::new(),
+            shutdown_summary: false,
+            message_escaping: MessageEscaping::default(),
+            console_writer: None,
+            run_marker: false,
+            format_marker: false,
+            flush_barrier: false,
+            flush_on_checkpoint: false,
+            level_padding: None,
+            timestamp_formatter: None,
+            disk_free_floor: None,
+            disk_check_interval: Duration::from_secs(10),
+            console_json: false,
+            event_ids: false,
+            run_id: None,
+        }
+    }
+}
+
+/// Represents a segment of a compiled log line template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TemplateSegment {
+    Literal(String),
+    Timestamp,
+    EventId,
+    RunId,
+    Color,
+    Glyph,
+    Level,
+    TraderId,
+    Component,
+    Message,
+}
+
+/// Represents a log line template parsed once into literal and placeholder segments, so
+/// formatting an event is a single pass rather than a chain of string replacements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LogTemplate {
+    source: String,
+    segments: Vec<TemplateSegment>,
+    literal_len: usize,
+}
+
+impl LogTemplate {
+    /// Parses the given template, keeping any unrecognized `{...}` placeholders as literal text.
+    fn new(template: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            literal.push_str(&rest[..start]);
+            let after = &rest[start..];
+            let Some(end) = after.find('}') else {
+                rest = after;
+                break;
+            };
+            let segment = match &after[1..end] {
+                "ts" => Some(TemplateSegment::Timestamp),
+                "event_id" => Some(TemplateSegment::EventId),
+                "run_id" => Some(TemplateSegment::RunId),
+                "color" => Some(TemplateSegment::Color),
+                "glyph" => Some(TemplateSegment::Glyph),
+                "level" => Some(TemplateSegment::Level),
+                "trader_id" => Some(TemplateSegment::TraderId),
+                "component" => Some(TemplateSegment::Component),
+                "message" => Some(TemplateSegment::Message),
+                _ => None,
+            };
+            match segment {
+                Some(segment) => {
+                    if !literal.is_empty() {
+                        segments.push(TemplateSegment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(segment);
+                }
+                None => literal.push_str(&after[..=end]),
+            }
+            rest = &after[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(TemplateSegment::Literal(literal));
+        }
+
+        let literal_len = segments
+            .iter()
+            .map(|segment| match segment {
+                TemplateSegment::Literal(literal) => literal.len(),
+                _ => 0,
+            })
+            .sum();
+
+        Self {
+            source: template.to_string(),
+            segments,
+            literal_len,
+        }
+    }
+
+    /// Returns the template with the `prefix` prepended as literal text (so any `{...}` in it is
+    /// not parsed as a placeholder).
+    fn with_prefix(mut self, prefix: &str) -> Self {
+        if !prefix.is_empty() {
+            self.source.insert_str(0, prefix);
+            self.segments
+                .insert(0, TemplateSegment::Literal(prefix.to_string()));
+            self.literal_len += prefix.len();
+        }
+        self
+    }
+}
+
+/// Provides formatting of log events into lines on the logging thread.
+struct LineFormatter {
+    trader_id: String,
+    timestamp_format: LogTimestampFormat,
+    json_field_names: JsonFieldNames,
+    message_escaping: MessageEscaping,
+    level_padding: Option<LevelPadding>,
+    timestamp_formatter: Option<TimestampFormatter>,
+    is_console_json: bool,
+    is_event_id: bool,
+    run_id: Option<String>,
+}
+
+impl LineFormatter {
+    fn new(trader_id: &str, config: &LoggerConfig) -> Self {
+        Self {
+            trader_id: trader_id.to_string(),
+            timestamp_format: config.timestamp_format,
+            json_field_names: config.json_field_names.clone(),
+            message_escaping: config.message_escaping,
+            level_padding: config.level_padding,
+            timestamp_formatter: config.timestamp_formatter.clone(),
+            is_console_json: config.console_json,
+            is_event_id: config.event_ids,
+            run_id: config.run_id.clone(),
+        }
+    }
+
+    fn format_timestamp(&self, timestamp: UnixNanos) -> String {
+        match &self.timestamp_formatter {
+            Some(formatter) => (formatter.0)(timestamp),
+            None => self.timestamp_format.format(timestamp),
+        }
+    }
+
+    /// Returns the escaped message with any identifiers appended as `key=value` pairs.
+    fn format_message<'a>(&self, event: &'a LogEvent) -> Cow<'a, str> {
+        let message = self.message_escaping.escape(&event.message);
+        let Some(identifiers) = &event.identifiers else {
+            return message;
+        };
+        let mut message = message.into_owned();
+        for (key, value) in identifiers.fields() {
+            message.push_str(&format!(" {key}={value}"));
+        }
+        Cow::Owned(message)
+    }
+
+    fn format_console(&self, event: &LogEvent, template: &LogTemplate, glyph: &str) -> String {
+        if self.is_console_json {
+            return format!("{}\n", self.format_json(event));
+        }
+        self.render(event, template, glyph, true)
+    }
+
+    fn format_file(
+        &self,
+        event: &LogEvent,
+        template: &LogTemplate,
+        is_json_format: bool,
+        glyph: &str,
+    ) -> String {
+        if is_json_format {
+            format!("{}\n", self.format_json(event))
+        } else {
+            self.render(event, template, glyph, false)
+        }
+    }
+
+    /// Fills the template segments for the event into a line allocated once up front.
+    ///
+    /// File lines carry no ANSI colors, so `{color}` is only filled for console lines.
+    fn render(
+        &self,
+        event: &LogEvent,
+        template: &LogTemplate,
+        glyph: &str,
+        is_console: bool,
+    ) -> String {
+        use std::fmt::Write as _;
+
+        let message = self.format_message(event);
+        let mut line = String::with_capacity(
+            template.literal_len
+                + self.trader_id.len()
+                + event.component.len()
+                + message.len()
+                + glyph.len()
+                + 64,
+        );
+        for segment in &template.segments {
+            match segment {
+                TemplateSegment::Literal(literal) => line.push_str(literal),
+                TemplateSegment::Timestamp => {
+                    line.push_str(&self.format_timestamp(event.timestamp));
+                }
+                TemplateSegment::EventId => {
+                    let _ = write!(line, "{}", event.seq);
+                }
+                TemplateSegment::RunId => line.push_str(self.run_id.as_deref().unwrap_or("")),
+                TemplateSegment::Color if is_console => {
+                    let _ = write!(line, "{}", event.color);
+                }
+                TemplateSegment::Color => line.push_str("{color}"),
+                TemplateSegment::Glyph => line.push_str(glyph),
+                TemplateSegment::Level => match &self.level_padding {
+                    Some(padding) => line.push_str(&padding.pad(event.level)),
+                    None => {
+                        let _ = write!(line, "{}", event.level);
+                    }
+                },
+                TemplateSegment::TraderId => line.push_str(&self.trader_id),
+                TemplateSegment::Component => line.push_str(&event.component),
+                TemplateSegment::Message => line.push_str(&message),
+            }
+        }
+        line
+    }
+
+    fn format_json(&self, event: &LogEvent) -> String {
+        let timestamp = self.timestamp_format.to_unix(event.timestamp);
+        self.json_field_names
+            .format(event, timestamp, self.is_event_id, self.run_id.as_deref())
+    }
+}
+
+/// Represents a command sent to the logging thread.
+#[derive(Debug)]
+enum LoggerCommand {
+    /// Handle a log event.
+    Event(LogEvent),
+    /// Handle a batch of log events contiguously, in order.
+    Batch(Vec<LogEvent>),
+    /// Stop writing log events, holding them until resumed.
+    Pause,
+    /// Write any held log events and continue writing.
+    Resume,
+    /// Flush and sync all log files, replying with the result once durable.
+    Checkpoint(SyncSender<io::Result<()>>),
+    /// Handle any remaining log events, then stop the logging thread.
+    Shutdown,
+}
+
+/// Provides the sending half of the channel to the logging thread.
+#[derive(Clone, Debug)]
+enum CommandSender {
+    Unbounded(Sender<LoggerCommand>),
+    Bounded(SyncSender<LoggerCommand>),
+}
+
+#[allow(clippy::result_large_err)] // The unsent command is returned as with `mpsc` senders
+impl CommandSender {
+    /// Sends the `command`, blocking while a bounded channel is full.
+    fn send(&self, command: LoggerCommand) -> Result<(), SendError<LoggerCommand>> {
+        match self {
+            Self::Unbounded(tx) => tx.send(command),
+            Self::Bounded(tx) => tx.send(command),
+        }
+    }
+
+    /// Sends the `command` without blocking, failing if a bounded channel is full.
+    fn try_send(&self, command: LoggerCommand) -> Result<(), TrySendError<LoggerCommand>> {
+        match self {
+            Self::Unbounded(tx) => tx
+                .send(command)
+                .map_err(|SendError(command)| TrySendError::Disconnected(command)),
+            Self::Bounded(tx) => tx.try_send(command),
+        }
+    }
+}
+
+/// Represents the state of a token bucket for a [`RateLimit`].
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: UnixNanos,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last: 0,
+        }
+    }
+
+    /// Returns the time until the next token is available (the limit is validated to refill).
+    fn wait_time(&self) -> Duration {
+        let secs = (1.0 - self.tokens).max(0.0) / self.limit.per_second;
+        Duration::from_secs_f64(secs).max(Duration::from_nanos(1))
+    }
+
+    fn try_take(&mut self, now: UnixNanos) -> bool {
+        if self.last != 0 {
+            let elapsed = now.saturating_sub(self.last) as f64 / 1_000_000_000.0;
+            self.tokens =
+                (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst as f64);
+        }
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Applies [`RateLimit`] rules on the logging thread, with a separate token bucket per rate
+/// domain so that a flood of events in one domain only throttles that domain.
+struct LogRateLimiter {
+    domains: HashMap<String, TokenBucket>,
+    default_bucket: Option<TokenBucket>,
+    action: RateLimitAction,
+    summary_interval: u64,
+    suppressed: u64,
+    suppressed_since: UnixNanos,
+    clock: LogClock,
+}
+
+impl LogRateLimiter {
+    fn new(
+        limits: HashMap<String, RateLimit>,
+        default_limit: Option<RateLimit>,
+        action: RateLimitAction,
+        summary_interval: Duration,
+        clock: LogClock,
+    ) -> Self {
+        Self {
+            domains: limits
+                .into_iter()
+                .map(|(domain, limit)| (domain, TokenBucket::new(limit)))
+                .collect(),
+            default_bucket: default_limit.map(TokenBucket::new),
+            action,
+            summary_interval: summary_interval.as_nanos() as u64,
+            suppressed: 0,
+            suppressed_since: 0,
+            clock,
+        }
+    }
+
+    /// Returns the number of events suppressed since the last summary, if the summary interval
+    /// has elapsed since the first of them.
+    fn take_summary(&mut self, now: UnixNanos) -> Option<u64> {
+        if self.suppressed == 0 || now.saturating_sub(self.suppressed_since) < self.summary_interval
+        {
+            return None;
+        }
+        Some(std::mem::take(&mut self.suppressed))
+    }
+
+    /// Returns the time until a summary of the suppressed events is due (zero if already due),
+    /// or `None` if no events have been suppressed since the last summary.
+    fn summary_due_in(&self, now: UnixNanos) -> Option<Duration> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        let due = self.suppressed_since.saturating_add(self.summary_interval);
+        Some(Duration::from_nanos(due.saturating_sub(now)))
+    }
+
+    /// Returns the number of events suppressed since the last summary regardless of the summary
+    /// interval, if any, such as for a final summary at shutdown.
+    fn take_remaining(&mut self) -> Option<u64> {
+        Some(std::mem::take(&mut self.suppressed)).filter(|&suppressed| suppressed > 0)
+    }
+
+    fn should_log(&mut self, event: &LogEvent, now: UnixNanos) -> bool {
+        let bucket = match event
+            .rate_domain
+            .as_ref()
+            .and_then(|domain| self.domains.get_mut(domain))
+        {
+            Some(bucket) => bucket,
+            None => match self.default_bucket.as_mut() {
+                Some(bucket) => bucket,
+                None => return true,
+            },
+        };
+        if bucket.try_take(now) {
+            return true;
+        }
+
+        match self.action {
+            RateLimitAction::Drop => false,
+            RateLimitAction::Summarize => {
+                if self.suppressed == 0 {
+                    self.suppressed_since = now;
+                }
+                self.suppressed += 1;
+                false
+            }
+            RateLimitAction::Block => loop {
+                thread::sleep(bucket.wait_time());
+                // The sleep can overshoot, so the budget is refilled as of the current time
+                if bucket.try_take(self.clock.now()) {
+                    return true;
+                }
+            },
+        }
+    }
+}
+
+/// Periodically checks the free disk space for log files, pausing file writes while it's below
+/// the configured floor.
+struct DiskSpaceMonitor {
+    directory: PathBuf,
+    floor: Option<u64>,
+    interval: u64,
+    last_check: Option<UnixNanos>,
+    is_paused: bool,
+}
+
+impl DiskSpaceMonitor {
+    fn new(directory: PathBuf, floor: Option<u64>, interval: Duration) -> Self {
+        Self {
+            directory,
+            floor,
+            interval: interval.as_nanos() as u64,
+            last_check: None,
+            is_paused: false,
+        }
+    }
+
+    /// Checks the free disk space (if due at `now`), returning a message describing any change
+    /// between paused and resumed file writes.
+    fn check(&mut self, now: UnixNanos) -> Option<(LogLevel, String)> {
+        let floor = self.floor?;
+        if self
+            .last_check
+            .is_some_and(|last_check| now.saturating_sub(last_check) < self.interval)
+        {
+            return None;
+        }
+        self.last_check = Some(now);
+
+        let free = Logger::available_disk_space(&self.directory)?;
+        let is_paused = free < floor;
+        if is_paused == self.is_paused {
+            return None;
+        }
+        self.is_paused = is_paused;
+
+        Some(if is_paused {
+            (
+                LogLevel::Warning,
+                format!(
+                    "Free disk space {free} bytes is below {floor} bytes, pausing file logging"
+                ),
+            )
+        } else {
+            (
+                LogLevel::Info,
+                format!("Free disk space {free} bytes has recovered, resuming file logging"),
+            )
+        })
+    }
+}
+
+/// Provides the counts of events logged per level and per component.
+#[derive(Clone, Debug, Default)]
+struct LogCounts {
+    levels: BTreeMap<LogLevel, u64>,
+    components: BTreeMap<String, u64>,
+}
+
+impl LogCounts {
+    /// Returns the lines of a summary block of the counts, along with the run `duration`
+    /// (nanoseconds) and the `dropped`, `sampled` and `rate_limited` counts.
+    fn summary(&self, duration: u64, dropped: u64, sampled: u64, rate_limited: u64) -> Vec<String> {
+        let levels: Vec<String> = LogLevel::iter()
+            .map(|level| format!("{level}={}", self.levels.get(&level).unwrap_or(&0)))
+            .collect();
+        let components: Vec<String> = self
+            .components
+            .iter()
+            .map(|(component, count)| format!("{component}={count}"))
+            .collect();
+        vec![
+            format!(
+                "Run summary: duration={}, dropped={dropped}, sampled={sampled}, rate_limited={rate_limited}",
+                format_duration(duration)
+            ),
+            format!("Run summary: levels=[{}]", levels.join(", ")),
+            format!("Run summary: components=[{}]", components.join(", ")),
+        ]
+    }
+}
+
+/// Represents the counts of events logged per level, and dropped, over an interval ending when
+/// the counts were taken with [`Logger::take_and_reset_counts`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogCountsSnapshot {
+    /// The count of debug events logged.
+    pub debug: u64,
+    /// The count of info events logged.
+    pub info: u64,
+    /// The count of warning events logged.
+    pub warning: u64,
+    /// The count of error events logged.
+    pub error: u64,
+    /// The count of critical events logged.
+    pub critical: u64,
+    /// The count of events dropped rather than logged (besides those sampled out or rate
+    /// limited).
+    pub dropped: u64,
+}
+
+impl LogCountsSnapshot {
+    fn record(&mut self, level: LogLevel) {
+        let count = match level {
+            LogLevel::Debug => &mut self.debug,
+            LogLevel::Info => &mut self.info,
+            LogLevel::Warning => &mut self.warning,
+            LogLevel::Error => &mut self.error,
+            LogLevel::Critical => &mut self.critical,
+        };
+        *count += 1;
+    }
+}
+
+/// Represents a subscriber to the live stream of logged events.
+#[cfg(feature = "log-stream")]
+enum LogSubscriber {
+    /// A [`Logger::subscribe`] channel, dropped once full.
+    Channel(SyncSender<String>),
+    /// A [`Logger::subscribe_with_ttl`] stream, removed once its [`LogStream`] is dropped.
+    Stream(Arc<LogStreamQueue>),
+}
+
+/// Provides the bounded queue of JSON events shared between the logging thread and a
+/// [`LogStream`], each held with the timestamp of its event.
+#[cfg(feature = "log-stream")]
+struct LogStreamQueue {
+    events: Mutex<LogStreamEvents>,
+    available: Condvar,
+    capacity: usize,
+    /// The maximum age (nanoseconds) of events received from the stream.
+    ttl: u64,
+    clock: LogClock,
+    /// The count of events not received, shared with the [`LoggerState`].
+    expired: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "log-stream")]
+struct LogStreamEvents {
+    queue: VecDeque<(UnixNanos, String)>,
+    /// If the logging thread has stopped, so no further events will be pushed.
+    is_closed: bool,
+}
+
+#[cfg(feature = "log-stream")]
+impl LogStreamQueue {
+    /// Pushes the event `json`, displacing the oldest event if the queue is full.
+    fn push(&self, timestamp: UnixNanos, json: String) {
+        let mut events = lock(&self.events);
+        if events.queue.len() >= self.capacity {
+            events.queue.pop_front();
+            self.expired.fetch_add(1, Ordering::Relaxed);
+        }
+        events.queue.push_back((timestamp, json));
+        self.available.notify_one();
+    }
+
+    fn close(&self) {
+        lock(&self.events).is_closed = true;
+        self.available.notify_all();
+    }
+
+    /// Pops the oldest event within the time-to-live, discarding (and counting) any older.
+    fn pop_fresh(&self, events: &mut LogStreamEvents) -> Option<String> {
+        let now = self.clock.now();
+        while let Some((timestamp, json)) = events.queue.pop_front() {
+            if now.saturating_sub(timestamp) <= self.ttl {
+                return Some(json);
+            }
+            self.expired.fetch_add(1, Ordering::Relaxed);
+        }
+        None
+    }
+}
+
+/// Represents the receiving end of a live stream of logged events with a time-to-live, opened
+/// with [`Logger::subscribe_with_ttl`].
+///
+/// Events older than the time-to-live when received are discarded rather than returned, so a
+/// consumer which falls behind always resumes with fresh events.
+#[cfg(feature = "log-stream")]
+pub struct LogStream {
+    queue: Arc<LogStreamQueue>,
+}
+
+#[cfg(feature = "log-stream")]
+impl LogStream {
+    /// Blocks until a fresh event is available, returning an error once the logging thread has
+    /// stopped and no events remain.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the stream is disconnected.
+    pub fn recv(&self) -> Result<String, RecvError> {
+        let mut events = lock(&self.queue.events);
+        loop {
+            if let Some(json) = self.queue.pop_fresh(&mut events) {
+                return Ok(json);
+            }
+            if events.is_closed {
+                return Err(RecvError);
+            }
+            events = self
+                .queue
+                .available
+                .wait(events)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+    }
+
+    /// Blocks until a fresh event is available or the `timeout` elapses.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the `timeout` elapses or the stream is disconnected.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut events = lock(&self.queue.events);
+        loop {
+            if let Some(json) = self.queue.pop_fresh(&mut events) {
+                return Ok(json);
+            }
+            if events.is_closed {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            events = self
+                .queue
+                .available
+                .wait_timeout(events, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Returns a fresh event if one is available without blocking.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if no fresh event is available or the stream is
+    /// disconnected.
+    pub fn try_recv(&self) -> Result<String, TryRecvError> {
+        let mut events = lock(&self.queue.events);
+        match self.queue.pop_fresh(&mut events) {
+            Some(json) => Ok(json),
+            None if events.is_closed => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns an iterator over the fresh events available without blocking.
+    pub fn try_iter(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::from_fn(|| self.try_recv().ok())
+    }
+}
+
+/// Tracks the sequence numbers of events the logging thread is done with, each either written to
+/// its sinks and flushed from the logger's buffers, or discarded without being written.
+///
+/// Events sent concurrently from several threads can reach the channel out of sequence order, so
+/// the flushed sequence number only advances once every event up to it is done with.
+#[derive(Debug, Default)]
+struct FlushedSeq {
+    /// The sequence number up to which all events are done with.
+    contiguous: u64,
+    /// The sequence numbers done with beyond the next expected one.
+    pending: BTreeSet<u64>,
+}
+
+impl FlushedSeq {
+    fn mark(&mut self, seq: u64) {
+        if seq <= self.contiguous {
+            return;
+        }
+        self.pending.insert(seq);
+        while self.pending.remove(&(self.contiguous + 1)) {
+            self.contiguous += 1;
+        }
+    }
+}
+
+/// Counts a send as in flight for as long as it's held.
+struct InFlightGuard<'a>(&'a AtomicU64);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Provides the state shared between a [`Logger`] and its logging thread.
+struct LoggerState {
+    /// The ring buffer of recently logged events.
+    buffer: Mutex<VecDeque<LogEvent>>,
+    /// The capacity of the ring buffer.
+    buffer_capacity: usize,
+    /// The count of events which were dropped rather than logged.
+    dropped: AtomicU64,
+    /// The count of events discarded by sampling.
+    sampled: AtomicU64,
+    /// The count of events discarded by rate limits.
+    rate_limited: AtomicU64,
+    /// The last error encountered while logging.
+    last_error: Mutex<Option<String>>,
+    /// The sequence number of the last event sent to the logging thread.
+    sent_seq: AtomicU64,
+    /// The sequence numbers of the events flushed or discarded by the logging thread.
+    flushed_seq: Mutex<FlushedSeq>,
+    /// Notifies waiters when the flushed sequence number advances.
+    flushed: Condvar,
+    /// If logged events are currently being captured.
+    capturing: AtomicBool,
+    /// The captured events not yet taken.
+    captured: Mutex<Vec<LogEvent>>,
+    /// The subscribers receiving a live stream of logged events as JSON.
+    #[cfg(feature = "log-stream")]
+    subscribers: Mutex<Vec<LogSubscriber>>,
+    /// The counts of logged events per level and component.
+    counts: Mutex<LogCounts>,
+    /// The counts of logged and dropped events since they were last taken.
+    interval_counts: Mutex<LogCountsSnapshot>,
+    /// The path of the main log file currently being written.
+    current_file_path: Mutex<Option<PathBuf>>,
+    /// If the logger is shutting down (and no longer accepts events).
+    shutting_down: AtomicBool,
+    /// The count of sends accepted but not yet enqueued on the channel, which shutdown waits on.
+    in_flight: AtomicU64,
+    /// The count of events rejected because the logger was shutting down.
+    rejected: AtomicU64,
+    /// The count of events not received from a [`LogStream`] because they exceeded its
+    /// time-to-live or were displaced from its full queue.
+    #[cfg(feature = "log-stream")]
+    expired: Arc<AtomicU64>,
+}
+
+impl LoggerState {
+    fn new(buffer_capacity: usize, capture: bool) -> Self {
+        Self {
+            buffer: Mutex::new(VecDeque::with_capacity(buffer_capacity)),
+            buffer_capacity,
+            dropped: AtomicU64::new(0),
+            sampled: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+            sent_seq: AtomicU64::new(0),
+            flushed_seq: Mutex::new(FlushedSeq::default()),
+            flushed: Condvar::new(),
+            capturing: AtomicBool::new(capture),
+            captured: Mutex::new(Vec::new()),
+            #[cfg(feature = "log-stream")]
+            subscribers: Mutex::new(Vec::new()),
+            counts: Mutex::new(LogCounts::default()),
+            interval_counts: Mutex::new(LogCountsSnapshot::default()),
+            current_file_path: Mutex::new(None),
+            shutting_down: AtomicBool::new(false),
+            in_flight: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            #[cfg(feature = "log-stream")]
+            expired: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns a guard counting the send as in flight until dropped, or an error (counting the
+    /// rejected event) if the logger is shutting down.
+    ///
+    /// The count is raised before the shutdown flag is read, so the logging thread either sees
+    /// the send in flight and waits for it, or the send sees the flag and is rejected.
+    fn check_accepting(&self) -> Result<InFlightGuard<'_>, LoggerShuttingDown> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard(&self.in_flight);
+        if self.shutting_down.load(Ordering::SeqCst) {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(LoggerShuttingDown);
+        }
+        Ok(guard)
+    }
+
+    /// Records the path of the main log file `sink`, if it's open.
+    fn set_current_file(&self, sink: Option<&FileSink>) {
+        *lock(&self.current_file_path) = sink
+            .filter(|sink| sink.buf.is_some())
+            .map(|sink| sink.path.clone());
+    }
+
+    fn count(&self, event: &LogEvent) {
+        lock(&self.interval_counts).record(event.level);
+        let mut counts = lock(&self.counts);
+        *counts.levels.entry(event.level).or_default() += 1;
+        match counts.components.get_mut(&event.component) {
+            Some(count) => *count += 1,
+            None => {
+                counts.components.insert(event.component.clone(), 1);
+            }
+        }
+    }
+
+    /// Publishes the `event` as JSON to all subscribers, dropping any channel subscriber which
+    /// has hung up or is too slow to keep up (its channel is full), and any stream whose
+    /// [`LogStream`] has been dropped, escaping non-ASCII characters if `is_ascii_only`.
+    #[cfg(feature = "log-stream")]
+    fn publish(&self, event: &LogEvent, formatter: &LineFormatter, is_ascii_only: bool) {
+        let mut subscribers = lock(&self.subscribers);
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let json = formatter.format_json(event);
+        let json = escape_non_ascii(&json, is_ascii_only, true).into_owned();
+        subscribers.retain(|subscriber| match subscriber {
+            LogSubscriber::Channel(tx) => match tx.try_send(json.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.record_error(String::from("Dropped slow log stream subscriber"));
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+            LogSubscriber::Stream(queue) => {
+                if Arc::strong_count(queue) == 1 {
+                    return false;
+                }
+                queue.push(event.timestamp, json.clone());
+                true
+            }
+        });
+    }
+
+    /// Disconnects all streams once the logging thread has stopped.
+    #[cfg(feature = "log-stream")]
+    fn close_streams(&self) {
+        for subscriber in lock(&self.subscribers).iter() {
+            if let LogSubscriber::Stream(queue) = subscriber {
+                queue.close();
+            }
+        }
+    }
+
+    fn capture(&self, event: &LogEvent) {
+        if self.capturing.load(Ordering::Relaxed) {
+            lock(&self.captured).push(event.clone());
+        }
+    }
+
+    /// Returns the next sequence number, such as for an event written by the logging thread.
+    fn next_seq(&self) -> u64 {
+        self.sent_seq.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Assigns the next sequence number to the `event` and sends it over the channel, returning
+    /// the sequence number.
+    fn send(&self, tx: &CommandSender, mut event: LogEvent) -> Result<u64, LoggerShuttingDown> {
+        let _in_flight = self.check_accepting()?;
+        let seq = self.next_seq();
+        event.seq = seq;
+        if let Err(SendError(LoggerCommand::Event(e))) = tx.send(LoggerCommand::Event(event)) {
+            self.record_drop();
+            self.mark_flushed(seq);
+            self.record_error(format!("Error sending log event: {}", e));
+        }
+        Ok(seq)
+    }
+
+    /// Assigns contiguous sequence numbers to the `events` and sends them over the channel as a
+    /// single batch, returning the sequence number of the last event.
+    fn send_batch(
+        &self,
+        tx: &CommandSender,
+        mut events: Vec<LogEvent>,
+    ) -> Result<u64, LoggerShuttingDown> {
+        let _in_flight = self.check_accepting()?;
+        let count = events.len() as u64;
+        let first = self.sent_seq.fetch_add(count, Ordering::Relaxed) + 1;
+        for (seq, event) in (first..).zip(&mut events) {
+            event.seq = seq;
+        }
+        if count > 0 && tx.send(LoggerCommand::Batch(events)).is_err() {
+            (first..first + count).for_each(|seq| {
+                self.record_drop();
+                self.mark_flushed(seq);
+            });
+            self.record_error(format!("Error sending batch of {count} log events"));
+        }
+        Ok(first + count - 1)
+    }
+
+    /// Assigns the next sequence number to the `event` and sends it over the channel without
+    /// blocking, returning whether the event was enqueued (otherwise it's counted as dropped).
+    fn try_send(&self, tx: &CommandSender, mut event: LogEvent) -> bool {
+        let Ok(_in_flight) = self.check_accepting() else {
+            return false;
+        };
+        let seq = self.next_seq();
+        event.seq = seq;
+        let is_sent = tx.try_send(LoggerCommand::Event(event)).is_ok();
+        if !is_sent {
+            self.record_drop();
+            self.mark_flushed(seq);
+        }
+        is_sent
+    }
+
+    /// Marks the event with the given `seq` as done with, once it's written and flushed or it's
+    /// discarded (events without a sequence number are ignored).
+    fn mark_flushed(&self, seq: u64) {
+        if seq == 0 {
+            return;
+        }
+        lock(&self.flushed_seq).mark(seq);
+        self.flushed.notify_all();
+    }
+
+    fn push_buffer(&self, event: &LogEvent) {
+        if self.buffer_capacity == 0 {
+            return;
+        }
+        let mut buffer = lock(&self.buffer);
+        if buffer.len() >= self.buffer_capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(event.clone());
+    }
+
+    fn record_drop(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        lock(&self.interval_counts).dropped += 1;
+    }
+
+    fn record_error(&self, error: String) {
+        eprintln!("{error}");
+        *lock(&self.last_error) = Some(error);
+    }
+}
+
+/// Represents a post-mortem snapshot of the logger state.
+#[derive(Serialize)]
+struct LoggerForensics {
+    trader_id: String,
+    machine_id: String,
+    instance_id: String,
+    dropped: u64,
+    sampled: u64,
+    rate_limited: u64,
+    last_error: Option<String>,
+    recent: Option<Vec<LogEvent>>,
+}
+
+/// Acquires the lock, recovering the inner value if the mutex was poisoned.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Attempts to acquire the lock without blocking, recovering the inner value if the
+/// mutex was poisoned.
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Tracks per-key event counts to apply [`LogSampling`] rules on the logging thread.
+///
+/// Events are keyed by `(component, key)` where the key is the caller-supplied sample key if
+/// provided, otherwise the message. The number of tracked keys is bounded, with the oldest keys
+/// evicted first (an evicted key starts counting again from zero).
+struct LogSampler {
+    rules: HashMap<String, LogSampling>,
+    default_rule: Option<LogSampling>,
+    max_keys: usize,
+    counts: HashMap<(String, String), u64>,
+    keys: VecDeque<(String, String)>,
+}
+
+impl LogSampler {
+    fn new(
+        rules: HashMap<String, LogSampling>,
+        default_rule: Option<LogSampling>,
+        max_keys: usize,
+    ) -> Self {
+        Self {
+            rules,
+            default_rule,
+            max_keys: max_keys.max(1),
+            counts: HashMap::new(),
+            keys: VecDeque::new(),
+        }
+    }
+
+    fn should_log(&mut self, event: &LogEvent) -> bool {
+        let rule = match self
+            .rules
+            .get(&event.component)
+            .or(self.default_rule.as_ref())
+        {
+            Some(rule) => *rule,
+            None => return true,
+        };
+
+        let key = (
+            event.component.clone(),
+            event
+                .sample_key
+                .clone()
+                .unwrap_or_else(|| event.message.clone()),
+        );
+
+        let count = if let Some(count) = self.counts.get_mut(&key) {
+            *count += 1;
+            *count
+        } else {
+            if self.keys.len() >= self.max_keys {
+                if let Some(oldest) = self.keys.pop_front() {
+                    self.counts.remove(&oldest);
+                }
+            }
+            self.keys.push_back(key.clone());
+            self.counts.insert(key, 1);
+            1
+        };
+
+        count <= rule.first || (rule.every > 0 && (count - rule.first) % rule.every == 0)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+impl Logger {
+    pub fn new(
+        trader_id: TraderId,
+        machine_id: String,
+        instance_id: UUID4,
+        level_stdout: LogLevel,
+        level_file: Option<LogLevel>,
+        directory: Option<String>,
+        file_name: Option<String>,
+        file_format: Option<String>,
+        component_levels: Option<HashMap<String, Value>>,
+        is_bypassed: bool,
+        mut config: LoggerConfig,
+    ) -> Self {
+        let (tx, rx) = match config.channel_capacity {
+            Some(capacity) => {
+                let (tx, rx) = sync_channel::<LoggerCommand>(capacity);
+                (CommandSender::Bounded(tx), rx)
+            }
+            None => {
+                let (tx, rx) = channel::<LoggerCommand>();
+                (CommandSender::Unbounded(tx), rx)
+            }
+        };
+        let mut level_filters = HashMap::<String, LogLevel>::new();
+
+        if let Some(component_levels_map) = component_levels {
+            for (key, value) in component_levels_map {
+                match serde_json::from_value::<LogLevel>(value) {
+                    Ok(level) => {
+                        level_filters.insert(key, level);
+                    }
+                    Err(e) => {
+                        // Handle the error, e.g. log a warning or ignore the entry
+                        eprintln!("Error parsing log level: {:?}", e);
+                    }
+                }
+            }
+        }
+
+        let trader_id_clone = trader_id.value.to_string();
+        let instance_id_clone = instance_id.to_string();
+        let state = Arc::new(LoggerState::new(config.buffer_capacity, config.capture));
+        let timer_level = config.timer_level;
+        let level_circular = config.circular_files.iter().map(|c| c.level).min();
+        if let Err(e) = check_valid_string(&config.default_component, "`default_component`") {
+            state.record_error(format!("Error in logger config: {e}"));
+            config.default_component = Arc::from(DEFAULT_COMPONENT);
+        }
+        if let Some(run_id) = config.run_id.take() {
+            match check_valid_string(&run_id, "`run_id`") {
+                Ok(()) => {
+                    config.run_id = Some(run_id.replace(['/', '\\'], "_").replace("..", "_"));
+                }
+                Err(e) => state.record_error(format!("Error in logger config: {e}")),
+            }
+        }
+        config.rate_limits.retain(|domain, limit| {
+            if !limit.is_valid() {
+                state.record_error(format!(
+                    "Error in logger config: invalid rate limit for domain '{domain}', \
+                     `per_second` was {}",
+                    limit.per_second
+                ));
+            }
+            limit.is_valid()
+        });
+        if let Some(limit) = config.rate_limit_default.filter(|limit| !limit.is_valid()) {
+            state.record_error(format!(
+                "Error in logger config: invalid default rate limit, `per_second` was {}",
+                limit.per_second
+            ));
+            config.rate_limit_default = None;
+        }
+        let default_component = config.default_component.clone();
+        let clock = config.clock.clone();
+        let state_clone = state.clone();
+
+        let handle = thread::spawn(move || {
+            Self::handle_messages(
+                &trader_id_clone,
+                &instance_id_clone,
+                level_stdout,
+                level_file,
+                directory,
+                file_name,
+                file_format,
+                level_filters,
+                config,
+                &state_clone,
+                rx,
+            )
+        });
+
+        Logger {
+            trader_id,
+            machine_id,
+            instance_id,
+            level_stdout,
+            level_file,
+            is_bypassed,
+            tx,
+            state,
+            handle: Some(handle),
+            timer_level,
+            level_circular,
+            default_component,
+            clock,
+        }
+    }
+
+    fn handle_messages(
+        trader_id: &str,
+        instance_id: &str,
+        level_stdout: LogLevel,
+        level_file: Option<LogLevel>,
+        directory: Option<String>,
+        file_name: Option<String>,
+        file_format: Option<String>,
+        level_filters: HashMap<String, LogLevel>,
+        config: LoggerConfig,
+        state: &LoggerState,
+        rx: Receiver<LoggerCommand>,
+    ) {
+        let clock = config.clock.clone();
+        let start = clock.now();
+
+        // Setup std I/O buffers
+        let mut out_buf = BufWriter::new(io::stdout());
+        let mut err_buf = BufWriter::new(io::stderr());
+
+        // Setup log file
+        let is_json_format = match file_format.as_ref().map(|s| s.to_lowercase()) {
+            Some(ref format) if format == "json" => true,
+            None => false,
+            Some(ref unrecognized) => {
+                eprintln!(
+                    "Unrecognized log file format: {}. Using plain text format as default.",
+                    unrecognized
+                );
+                false
+            }
+        };
+
+        // Setup templates for formatting (ANSI colors are stripped for any console stream which
+        // can't render them)
+        let console_colors = Self::enable_console_colors();
+        let separator = &config.component_separator;
+        let mut template_console =
+            Self::create_console_template(config.console_color_scope, separator);
+        let mut template_file = format!(
+            "{{ts}} {{glyph}}[{{level}}] {{trader_id}}{separator}{{component}}: {{message}}\n"
+        );
+        if config.event_ids {
+            template_console = template_console.replacen("{ts}", "{ts} #{event_id}", 1);
+            template_file = template_file.replacen("{ts}", "{ts} #{event_id}", 1);
+        }
+        if config.run_id.is_some() {
+            template_console = template_console.replacen("{ts}", "{ts} run_id={run_id}", 1);
+            template_file = template_file.replacen("{ts}", "{ts} run_id={run_id}", 1);
+        }
+        let create_console_template = |template: &str| match &config.console_prefix {
+            Some(prefix) if !config.console_json => LogTemplate::new(template).with_prefix(prefix),
+            _ => LogTemplate::new(template),
+        };
+        let template_console_colored = create_console_template(&template_console);
+        let template_console_plain = create_console_template(&template_file);
+        let template_file = LogTemplate::new(&template_file);
+        let template_console = |level: LogLevel| {
+            if config.console_writer.is_some()
+                || console_colors.is_colored(level, config.console_stream_policy)
+            {
+                &template_console_colored
+            } else {
+                &template_console_plain
+            }
+        };
+
+        // The main log file is always the first file sink, followed by any additional outputs
+        let mut file_date = Utc::now().date_naive();
+        let mut file_sinks = Vec::new();
+        if level_file.is_some() {
+            let file_path = Self::create_log_file_path(
+                &directory,
+                &file_name,
+                trader_id,
+                instance_id,
+                config.run_id.as_deref(),
+                is_json_format,
+            );
+            file_sinks.push(FileSink::open(
+                file_path,
+                is_json_format,
+                config.file_ascii_only,
+                config.file_conflict,
+                state,
+            ));
+
+            for output in &config.file_outputs {
+                let is_json_format = output.format == LogFileFormat::Json;
+                // An output whose directories can't be created is skipped, keeping the others
+                if let Some(Err(e)) = output.path.parent().map(create_dir_all) {
+                    state.record_error(format!(
+                        "Error creating directories for log file {}: {e:?}",
+                        output.path.display()
+                    ));
+                    file_sinks.push(FileSink::closed(
+                        output.path.clone(),
+                        is_json_format,
+                        output.ascii_only,
+                    ));
+                    continue;
+                }
+                file_sinks.push(FileSink::open(
+                    output.path.clone(),
+                    is_json_format,
+                    output.ascii_only,
+                    config.file_conflict,
+                    state,
+                ));
+            }
+
+            for sink in &mut file_sinks {
+                if config.format_marker {
+                    sink.write_format_marker(&template_file, &config.json_field_names, state);
+                }
+                sink.write_header(&config.file_header, state);
+                if config.run_marker {
+                    sink.write_run_marker(instance_id, start, state);
+                }
+            }
+            state.set_current_file(file_sinks.first());
+        }
+
+        // Only default file names are rotated, so retention is not applicable to custom names
+        let file_retention = config.file_retention.filter(|_| file_name.is_none());
+        if let (Some(retention), Some(sink)) = (file_retention, file_sinks.first()) {
+            Self::apply_file_retention(&retention, &directory, trader_id, &sink.path, state);
+        }
+
+        let circular_sinks: Vec<CircularFileSink> = config
+            .circular_files
+            .iter()
+            .map(|output| CircularFileSink::open(output, state))
+            .collect();
+
+        let disk_directory = match file_sinks.first().and_then(|sink| sink.path.parent()) {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut disk_monitor = DiskSpaceMonitor::new(
+            disk_directory,
+            config.disk_free_floor,
+            config.disk_check_interval,
+        );
+
+        let mut write_console = |line: &str, level: LogLevel| {
+            let line = &escape_non_ascii(line, config.console_ascii_only, config.console_json);
+            if let Some(writer) = &config.console_writer {
+                writer.write_line(line, state);
+            } else if config.console_stream_policy.is_stderr(level) {
+                Self::write_stderr(&mut err_buf, line, state);
+                Self::flush_stderr(&mut err_buf, state);
+            } else {
+                Self::write_stdout(&mut out_buf, line, state);
+                Self::flush_stdout(&mut out_buf, state);
+            }
+        };
+
+        let formatter = LineFormatter::new(trader_id, &config);
+        let mut sampler = LogSampler::new(
+            config.sampling,
+            config.sampling_default,
+            config.sampling_max_keys,
+        );
+        // The rate limiter is also checked for due summaries between events
+        let rate_limiter = RefCell::new(LogRateLimiter::new(
+            config.rate_limits,
+            config.rate_limit_default,
+            config.rate_limit_action,
+            config.rate_limit_summary_interval,
+            clock.clone(),
+        ));
+
+        // The sinks are also flushed by checkpoints between events
+        let file_sinks = RefCell::new(file_sinks);
+        let circular_sinks = RefCell::new(circular_sinks);
+
+        // The events written to log files awaiting a flush (when only flushed by checkpoints),
+        // which are marked as flushed once the log files are
+        let unflushed = RefCell::new(Vec::new());
+        let mark_unflushed = || {
+            for seq in unflushed.borrow_mut().drain(..) {
+                state.mark_flushed(seq);
+            }
+        };
+
+        // Writes an event which passed filtering to all sinks
+        let write_event = RefCell::new(|event: LogEvent| {
+            let mut file_sinks = file_sinks.borrow_mut();
+            let mut circular_sinks = circular_sinks.borrow_mut();
+            state.count(&event);
+            state.push_buffer(&event);
+            state.capture(&event);
+            #[cfg(feature = "log-stream")]
+            state.publish(&event, &formatter, config.subscriber_ascii_only);
+
+            let glyph = config.glyphs.get(&event.level).map_or("", String::as_str);
+            let mut is_unflushed = false;
+
+            if config.console_stream_policy.is_stderr(event.level)
+                || event.level >= level_stdout
+                || event.level >= LogLevel::Error
+            {
+                let line = formatter.format_console(&event, template_console(event.level), glyph);
+                write_console(&line, event.level);
+            }
+
+            if let Some(level_file) = level_file {
+                if Self::should_rotate_file(file_date) {
+                    // Ensure previous file buffers flushed
+                    for buf in file_sinks.iter_mut().filter_map(|s| s.buf.as_mut()) {
+                        Self::flush_file(buf, state);
+                    }
+                    mark_unflushed();
+
+                    let file_path = Self::create_log_file_path(
+                        &directory,
+                        &file_name,
+                        trader_id,
+                        instance_id,
+                        config.run_id.as_deref(),
+                        is_json_format,
+                    );
+                    file_date = Utc::now().date_naive();
+
+                    // The main log file moves to its new path, additional outputs to dated paths
+                    for (i, sink) in file_sinks.iter_mut().enumerate() {
+                        let path = if i == 0 {
+                            file_path.clone()
+                        } else {
+                            Self::dated_log_file_path(&config.file_outputs[i - 1].path, file_date)
+                        };
+                        sink.reopen(path, config.file_conflict, state);
+                        if config.format_marker {
+                            sink.write_format_marker(
+                                &template_file,
+                                &config.json_field_names,
+                                state,
+                            );
+                        }
+                        sink.write_header(&config.file_header, state);
+                        if config.run_marker {
+                            sink.write_run_marker(instance_id, start, state);
+                        }
+                    }
+                    state.set_current_file(file_sinks.first());
+
+                    if let (Some(retention), Some(sink)) = (&file_retention, file_sinks.first()) {
+                        Self::apply_file_retention(
+                            retention, &directory, trader_id, &sink.path, state,
+                        );
+                    }
+                }
+
+                if event.level >= level_file {
+                    if let Some((level, message)) = disk_monitor.check(clock.now()) {
+                        let disk_event = LogEvent {
+                            timestamp: event.timestamp,
+                            level,
+                            color: LogColor::Normal,
+                            component: config.default_component.to_string(),
+                            message,
+                            sample_key: None,
+                            rate_domain: None,
+                            seq: state.next_seq(),
+                            identifiers: None,
+                        };
+                        let line =
+                            formatter.format_console(&disk_event, template_console(level), "");
+                        write_console(&line, level);
+                        state.mark_flushed(disk_event.seq);
+                    }
+                }
+
+                if event.level >= level_file && !disk_monitor.is_paused {
+                    for sink in file_sinks.iter_mut() {
+                        let Some(buf) = sink.buf.as_mut() else {
+                            continue;
+                        };
+                        let line = formatter.format_file(
+                            &event,
+                            &template_file,
+                            sink.is_json_format,
+                            glyph,
+                        );
+                        let line = escape_non_ascii(&line, sink.is_ascii_only, sink.is_json_format);
+                        Self::write_file(buf, &line, state);
+                        is_unflushed = config.flush_on_checkpoint;
+                        if !config.flush_on_checkpoint {
+                            if config.flush_barrier {
+                                Self::write_file(
+                                    buf,
+                                    FileSink::barrier(sink.is_json_format),
+                                    state,
+                                );
+                            }
+                            Self::flush_file(buf, state);
+                        }
+                    }
+                }
+            }
+
+            for sink in circular_sinks.iter_mut() {
+                if event.level >= sink.level {
+                    let line =
+                        formatter.format_file(&event, &template_file, sink.is_json_format, glyph);
+                    sink.write_line(&line, state);
+                }
+            }
+
+            if is_unflushed {
+                unflushed.borrow_mut().push(event.seq);
+            } else {
+                state.mark_flushed(event.seq);
+            }
+        });
+
+        // Writes a warning of how many events were suppressed by rate limits
+        let write_summary = |now: UnixNanos, suppressed: u64| {
+            (write_event.borrow_mut())(LogEvent {
+                timestamp: now,
+                level: LogLevel::Warning,
+                color: LogColor::Yellow,
+                component: config.default_component.to_string(),
+                message: format!("{suppressed} messages suppressed by rate limit"),
+                sample_key: None,
+                rate_domain: None,
+                seq: state.next_seq(),
+                identifiers: None,
+            });
+        };
+        let write_due_summary = |now: UnixNanos| {
+            let summary = rate_limiter.borrow_mut().take_summary(now);
+            if let Some(suppressed) = summary {
+                write_summary(now, suppressed);
+            }
+        };
+
+        let mut handle_event = |mut event: LogEvent| {
+            if event.message.is_empty() {
+                match config.empty_message_policy {
+                    EmptyMessagePolicy::Keep => {}
+                    EmptyMessagePolicy::Drop => {
+                        state.mark_flushed(event.seq);
+                        return;
+                    }
+                    EmptyMessagePolicy::Placeholder => event.message = String::from("<empty>"),
+                }
+            }
+
+            if let Some(remap) = config.level_remaps.iter().find(|r| r.matches(&event)) {
+                event.level = remap.to;
+            }
+
+            let component_level = level_filters.get(&event.component);
+
+            // Check if the component exists in level_filters and if its level is greater than event.level
+            if let Some(&filter_level) = component_level {
+                if event.level < filter_level {
+                    state.mark_flushed(event.seq);
+                    return;
+                }
+            }
+
+            let now = clock.now();
+            let is_sampled_out = !sampler.should_log(&event);
+            let is_logged = !is_sampled_out && rate_limiter.borrow_mut().should_log(&event, now);
+            write_due_summary(now);
+            if !is_logged {
+                let count = if is_sampled_out {
+                    &state.sampled
+                } else {
+                    &state.rate_limited
+                };
+                count.fetch_add(1, Ordering::Relaxed);
+                state.mark_flushed(event.seq);
+                return;
+            }
+
+            (write_event.borrow_mut())(event);
+        };
+
+        // Events received while paused are held (up to the configured bound) until resumed
+        let pause_capacity = config.pause_capacity;
+        let mut paused = false;
+        let mut pending = VecDeque::new();
+
+        // Continue to receive and handle commands until channel is hung up, waking to write any
+        // rate limit summary which falls due while no events arrive
+        loop {
+            let summary_due_in = if paused {
+                None
+            } else {
+                rate_limiter.borrow().summary_due_in(clock.now())
+            };
+            let command = match summary_due_in {
+                Some(timeout) => match rx.recv_timeout(timeout) {
+                    Ok(command) => command,
+                    Err(RecvTimeoutError::Timeout) => {
+                        write_due_summary(clock.now());
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(command) => command,
+                    Err(_) => break,
+                },
+            };
+            match command {
+                LoggerCommand::Event(event) if paused => {
+                    if pending.len() < pause_capacity {
+                        pending.push_back(event);
+                    } else {
+                        state.record_drop();
+                        state.mark_flushed(event.seq);
+                    }
+                }
+                LoggerCommand::Event(event) => handle_event(event),
+                LoggerCommand::Batch(events) if paused => {
+                    for event in events {
+                        if pending.len() < pause_capacity {
+                            pending.push_back(event);
+                        } else {
+                            state.record_drop();
+                            state.mark_flushed(event.seq);
+                        }
+                    }
+                }
+                LoggerCommand::Batch(events) => events.into_iter().for_each(&mut handle_event),
+                LoggerCommand::Checkpoint(reply) => {
+                    let result = Self::checkpoint_files(
+                        &mut file_sinks.borrow_mut(),
+                        &mut circular_sinks.borrow_mut(),
+                        config.flush_barrier && config.flush_on_checkpoint,
+                        state,
+                    );
+                    mark_unflushed();
+                    let _ = reply.send(result);
+                }
+                LoggerCommand::Pause => paused = true,
+                LoggerCommand::Resume => {
+                    paused = false;
+                    pending.drain(..).for_each(&mut handle_event);
+                }
+                LoggerCommand::Shutdown => break,
+            }
+        }
+
+        // Handle any events still held from a pause, then any sent as shutdown began (including
+        // those from sends accepted but not yet enqueued, once they reach the channel)
+        pending.drain(..).for_each(&mut handle_event);
+        let mut handle_drained = |command: LoggerCommand| match command {
+            LoggerCommand::Event(event) => handle_event(event),
+            LoggerCommand::Batch(events) => events.into_iter().for_each(&mut handle_event),
+            LoggerCommand::Checkpoint(reply) => {
+                let result = Self::checkpoint_files(
+                    &mut file_sinks.borrow_mut(),
+                    &mut circular_sinks.borrow_mut(),
+                    config.flush_barrier && config.flush_on_checkpoint,
+                    state,
+                );
+                mark_unflushed();
+                let _ = reply.send(result);
+            }
+            _ => {}
+        };
+        loop {
+            let is_idle = state.in_flight.load(Ordering::SeqCst) == 0;
+            rx.try_iter().for_each(&mut handle_drained);
+            if is_idle {
+                break;
+            }
+            // Wait on the channel for the sends in flight, waking periodically to recheck the
+            // count as a rejected send leaves without enqueuing anything
+            match rx.recv_timeout(DRAIN_POLL_INTERVAL) {
+                Ok(command) => handle_drained(command),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let remaining = rate_limiter.borrow_mut().take_remaining();
+        if let Some(suppressed) = remaining {
+            write_summary(clock.now(), suppressed);
+        }
+
+        // The run summary is written directly to the sinks, so it isn't filtered or counted
+        if config.shutdown_summary {
+            let now = clock.now();
+            let lines = lock(&state.counts).summary(
+                now.saturating_sub(start),
+                state.dropped.load(Ordering::Relaxed),
+                state.sampled.load(Ordering::Relaxed),
+                state.rate_limited.load(Ordering::Relaxed),
+            );
+            let glyph = config
+                .glyphs
+                .get(&LogLevel::Info)
+                .map_or("", String::as_str);
+            let mut file_sinks = file_sinks.borrow_mut();
+            for message in lines {
+                let event = LogEvent {
+                    timestamp: now,
+                    level: LogLevel::Info,
+                    color: LogColor::Normal,
+                    component: config.default_component.to_string(),
+                    message,
+                    sample_key: None,
+                    rate_domain: None,
+                    seq: state.next_seq(),
+                    identifiers: None,
+                };
+                if LogLevel::Info >= level_stdout {
+                    let line =
+                        formatter.format_console(&event, template_console(event.level), glyph);
+                    write_console(&line, event.level);
+                }
+                if level_file.is_some() {
+                    for sink in file_sinks.iter_mut() {
+                        let Some(buf) = sink.buf.as_mut() else {
+                            continue;
+                        };
+                        let line = formatter.format_file(
+                            &event,
+                            &template_file,
+                            sink.is_json_format,
+                            glyph,
+                        );
+                        let line = escape_non_ascii(&line, sink.is_ascii_only, sink.is_json_format);
+                        Self::write_file(buf, &line, state);
+                    }
+                }
+                state.mark_flushed(event.seq);
+            }
+        }
+
+        // Finally ensure remaining buffers are flushed
+        Self::flush_stderr(&mut err_buf, state);
+        Self::flush_stdout(&mut out_buf, state);
+        for buf in file_sinks
+            .borrow_mut()
+            .iter_mut()
+            .filter_map(|s| s.buf.as_mut())
+        {
+            Self::flush_file(buf, state);
+        }
+        mark_unflushed();
+        for sink in circular_sinks.borrow_mut().iter_mut() {
+            if let Err(e) = sink.flush() {
+                state.record_error(format!("Error writing to circular log file: {e:?}"));
+            }
+        }
+        #[cfg(feature = "log-stream")]
+        state.close_streams();
+    }
+
+    /// Flushes the log files (including the circular log file headers) and syncs them to disk,
+    /// first writing a barrier to each if `is_barrier`.
+    fn checkpoint_files(
+        file_sinks: &mut [FileSink],
+        circular_sinks: &mut [CircularFileSink],
+        is_barrier: bool,
+        state: &LoggerState,
+    ) -> io::Result<()> {
+        let result = file_sinks
+            .iter_mut()
+            .filter_map(|sink| {
+                let is_json_format = sink.is_json_format;
+                sink.buf.as_mut().map(|buf| (buf, is_json_format))
+            })
+            .try_for_each(|(buf, is_json_format)| {
+                if is_barrier {
+                    buf.write_all(FileSink::barrier(is_json_format).as_bytes())?;
+                }
+                buf.flush()?;
+                buf.get_ref().sync_all()
+            })
+            .and_then(|_| {
+                circular_sinks.iter_mut().try_for_each(|sink| {
+                    sink.flush()?;
+                    sink.file.as_ref().map_or(Ok(()), File::sync_all)
+                })
+            });
+        if let Err(e) = &result {
+            state.record_error(format!("Error checkpointing log files: {e:?}"));
+        }
+        result
+    }
+
+    /// Enables virtual terminal processing for the Windows console so that ANSI escape sequences
+    /// render as colors, returning whether this succeeded for each of stdout and stderr.
+    ///
+    /// Colors are only disabled for a stream whose handle failed, such as stdout redirected to a
+    /// file, so the other stream keeps its colors.
+    #[cfg(windows)]
+    fn enable_console_colors() -> ConsoleColors {
+        use std::os::windows::io::AsRawHandle;
+
+        ConsoleColors {
+            stdout: Self::enable_virtual_terminal(io::stdout().as_raw_handle()),
+            stderr: Self::enable_virtual_terminal(io::stderr().as_raw_handle()),
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn enable_console_colors() -> ConsoleColors {
+        ConsoleColors {
+            stdout: true,
+            stderr: true,
+        }
+    }
+
+    /// Enables virtual terminal processing for the console `handle`, returning `false` if it's
+    /// not a console or the mode could not be set.
+    #[cfg(windows)]
+    fn enable_virtual_terminal(handle: std::os::windows::io::RawHandle) -> bool {
+        const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetConsoleMode(handle: std::os::windows::io::RawHandle, mode: *mut u32) -> i32;
+            fn SetConsoleMode(handle: std::os::windows::io::RawHandle, mode: u32) -> i32;
+        }
+
+        let mut mode: u32 = 0;
+        // Safety: The console functions fail without side effects for handles which aren't consoles
+        unsafe {
+            GetConsoleMode(handle, &mut mode) != 0
+                && (mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                    || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0)
+        }
+    }
+
+    /// Returns the disk space in bytes available to the process on the filesystem containing
+    /// `path`, or `None` if it could not be determined.
+    #[cfg(unix)]
+    fn available_disk_space(path: &Path) -> Option<u64> {
+        use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        // Safety: `path` is a valid C string and `stat` is only read once initialized
+        unsafe {
+            if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let stat = stat.assume_init();
+            #[allow(clippy::unnecessary_cast)] // Field types vary by platform
+            let available = stat.f_bavail as u64 * stat.f_frsize as u64;
+            Some(available)
+        }
+    }
+
+    #[cfg(windows)]
+    fn available_disk_space(path: &Path) -> Option<u64> {
+        use std::os::windows::ffi::OsStrExt;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetDiskFreeSpaceExW(
+                directory: *const u16,
+                free_bytes_available: *mut u64,
+                total_bytes: *mut u64,
+                total_free_bytes: *mut u64,
+            ) -> i32;
+        }
+
+        let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free: u64 = 0;
+        // Safety: `path` is a null terminated wide string and the null outputs are optional
+        let is_ok = unsafe {
+            GetDiskFreeSpaceExW(
+                path.as_ptr(),
+                &mut free,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            ) != 0
+        };
+        is_ok.then_some(free)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn available_disk_space(_path: &Path) -> Option<u64> {
+        None
+    }
+
+    fn should_rotate_file(file_date: NaiveDate) -> bool {
+        Utc::now().date_naive() != file_date
+    }
+
+    /// Returns the `path` with the `date` appended to its file stem, such as
+    /// `trades_2024-01-02.json` for `trades.json`.
+    fn dated_log_file_path(path: &Path, date: NaiveDate) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut file_name = format!("{stem}_{}", date.format("%Y-%m-%d"));
+        if let Some(extension) = path.extension() {
+            file_name.push('.');
+            file_name.push_str(&extension.to_string_lossy());
+        }
+        path.with_file_name(file_name)
+    }
+
+    /// Returns whether the `file_name` matches the default log file naming pattern for the trader.
+    fn is_rotated_log_file(file_name: &str, trader_id: &str) -> bool {
+        let Some(stem) = file_name
+            .strip_suffix(".log")
+            .or_else(|| file_name.strip_suffix(".json"))
+        else {
+            return false;
+        };
+        let Some(rest) = stem
+            .strip_prefix(trader_id)
+            .and_then(|s| s.strip_prefix('_'))
+        else {
+            return false;
+        };
+        match (rest.get(..10), rest.get(10..)) {
+            (Some(date), Some(instance_id)) => {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+                    && instance_id.len() > 1
+                    && instance_id.starts_with('_')
+            }
+            _ => false,
+        }
+    }
+
+    /// Deletes rotated log files in the log directory which exceed the `retention` limits,
+    /// never touching the currently active file, files held open by another logger or files not
+    /// matching the naming pattern.
+    fn apply_file_retention(
+        retention: &FileRetention,
+        directory: &Option<String>,
+        trader_id: &str,
+        current_file_path: &Path,
+        state: &LoggerState,
+    ) {
+        let dir = directory.as_deref().unwrap_or(".");
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                state.record_error(format!("Error reading log directory: {e:?}"));
+                return;
+            }
+        };
+
+        let mut rotated_files: Vec<(PathBuf, SystemTime, u64)> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+                Some(file_name.as_ref()) != current_file_path.file_name().and_then(|n| n.to_str())
+                    && Self::is_rotated_log_file(&file_name, trader_id)
+            })
+            // Files still being written by another logger are not rotated out yet
+            .filter(|entry| !is_log_file_in_use(&entry.path()))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().ok()?;
+                metadata
+                    .is_file()
+                    .then(|| (entry.path(), modified, metadata.len()))
+            })
+            .collect();
+
+        // Sort newest first so the most recent files are retained
+        rotated_files.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+        let now = SystemTime::now();
+        let mut total_bytes = 0;
+        for (i, (path, modified, len)) in rotated_files.iter().enumerate() {
+            total_bytes += len;
+            let is_expired = retention.max_age.is_some_and(|max_age| {
+                now.duration_since(*modified).is_ok_and(|age| age > max_age)
+            });
+            let is_excess_count = retention.max_files.is_some_and(|max| i >= max);
+            let is_excess_size = retention
+                .max_total_bytes
+                .is_some_and(|max| total_bytes > max);
+
+            if is_expired || is_excess_count || is_excess_size {
+                if let Err(e) = std::fs::remove_file(path) {
+                    state.record_error(format!("Error removing rotated log file: {e:?}"));
+                }
+            }
+        }
+    }
+
+    fn default_log_file_basename(trader_id: &str, instance_id: &str) -> String {
+        let current_date_utc = Utc::now().format("%Y-%m-%d");
+        format!("{}_{}_{}", trader_id, current_date_utc, instance_id)
+    }
+
+    fn create_log_file_path(
+        directory: &Option<String>,
+        file_name: &Option<String>,
+        trader_id: &str,
+        instance_id: &str,
+        run_id: Option<&str>,
+        is_json_format: bool,
+    ) -> PathBuf {
+        let basename = match (file_name, run_id) {
+            (Some(file_name), Some(run_id)) => format!("{file_name}_{run_id}"),
+            (Some(file_name), None) => file_name.to_owned(),
+            (None, Some(run_id)) => {
+                Self::default_log_file_basename(trader_id, &format!("{run_id}_{instance_id}"))
+            }
+            (None, None) => Self::default_log_file_basename(trader_id, instance_id),
+        };
+
+        let suffix = if is_json_format { "json" } else { "log" };
+        let mut file_path = PathBuf::new();
+
+        if let Some(directory) = directory {
+            file_path.push(directory);
+            create_dir_all(&file_path).expect("Failed to create directories for log file");
+        }
+
+        file_path.push(basename);
+        file_path.set_extension(suffix);
+        file_path
+    }
+
+    fn create_console_template(scope: ConsoleColorScope, separator: &str) -> String {
+        let (level, message) = match scope {
+            ConsoleColorScope::WholeLine => (
+                String::from("{color}{glyph}[{level}]"),
+                String::from("{message}\x1b[0m"),
+            ),
+            ConsoleColorScope::LevelOnly => (
+                String::from("{color}{glyph}[{level}]\x1b[0m"),
+                String::from("{message}"),
+            ),
+            ConsoleColorScope::MessageOnly => (
+                String::from("{glyph}[{level}]"),
+                String::from("{color}{message}\x1b[0m"),
+            ),
+            ConsoleColorScope::None => {
+                (String::from("{glyph}[{level}]"), String::from("{message}"))
+            }
+        };
+        format!("\x1b[1m{{ts}}\x1b[0m {level} {{trader_id}}{separator}{{component}}: {message}\n")
+    }
+
+    fn write_stdout(out_buf: &mut BufWriter<Stdout>, line: &str, state: &LoggerState) {
+        match out_buf.write_all(line.as_bytes()) {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error writing to stdout: {e:?}")),
+        }
+    }
+
+    fn flush_stdout(out_buf: &mut BufWriter<Stdout>, state: &LoggerState) {
+        match out_buf.flush() {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error flushing stdout: {e:?}")),
+        }
+    }
+
+    fn write_stderr(err_buf: &mut BufWriter<Stderr>, line: &str, state: &LoggerState) {
+        match err_buf.write_all(line.as_bytes()) {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error writing to stderr: {e:?}")),
+        }
+    }
+
+    fn flush_stderr(err_buf: &mut BufWriter<Stderr>, state: &LoggerState) {
+        match err_buf.flush() {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error flushing stderr: {e:?}")),
+        }
+    }
+
+    fn write_file(file_buf: &mut BufWriter<File>, line: &str, state: &LoggerState) {
+        match file_buf.write_all(line.as_bytes()) {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error writing to file: {e:?}")),
+        }
+    }
+
+    fn flush_file(file_buf: &mut BufWriter<File>, state: &LoggerState) {
+        match file_buf.flush() {
+            Ok(_) => {}
+            Err(e) => state.record_error(format!("Error writing to file: {e:?}")),
+        }
+    }
+
+    /// Sends a log event to the logging thread, returning a flush token which can be passed to
+    /// [`Logger::wait_flushed`] to block until the event has been written and flushed (or
+    /// discarded).
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if [`Logger::shutdown`] has begun, in which case the event
+    /// is not logged (and is counted in [`Logger::rejected_count`]).
+    pub fn send(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+    ) -> Result<u64, LoggerShuttingDown> {
+        let event = LogEvent {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        };
+        self.send_event(event)
+    }
+
+    /// Sends a log event which is sampled by the given `sample_key` rather than its message.
+    ///
+    /// This allows repetitive events with varying message content (such as embedded values) to
+    /// share a single sampling budget.
+    pub fn send_with_sample_key(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+        sample_key: String,
+    ) -> Result<u64, LoggerShuttingDown> {
+        let event = LogEvent {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: Some(sample_key),
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        };
+        self.send_event(event)
+    }
+
+    /// Sends a log event which is rate limited by the budget of the given `rate_domain`.
+    ///
+    /// Each domain configured in [`LoggerConfig::rate_limits`] has its own budget, so a flood of
+    /// events in one domain doesn't throttle events in other domains.
+    pub fn send_with_rate_domain(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+        rate_domain: String,
+    ) -> Result<u64, LoggerShuttingDown> {
+        let event = LogEvent {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: None,
+            rate_domain: Some(rate_domain),
+            seq: 0,
+            identifiers: None,
+        };
+        self.send_event(event)
+    }
+
+    /// Attempts to send a log event without ever blocking, returning whether it was enqueued.
+    ///
+    /// If the channel is full (or disconnected) the event is dropped and counted in
+    /// [`Logger::dropped_count`], making this suitable for latency-critical paths.
+    pub fn try_log(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+    ) -> bool {
+        let event = LogEvent {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        };
+        self.state.try_send(&self.tx, event)
+    }
+
+    /// Writes a fixed `message` line directly to the stderr file descriptor, bypassing the
+    /// logging thread, returning whether the whole line was written.
+    ///
+    /// This path is async-signal-safe (there is no allocation, locking or formatting), so it can
+    /// be called from a signal handler to leave a final breadcrumb before a crash. The line is
+    /// written as the level followed by the message, without a timestamp or component.
+    #[cfg(unix)]
+    pub fn emergency(level: LogLevel, message: &'static str) -> bool {
+        Self::emergency_to_fd(libc::STDERR_FILENO, level, message)
+    }
+
+    /// Writes a fixed `message` line directly to stderr, bypassing the logging thread, returning
+    /// whether the whole line was written.
+    ///
+    /// On this platform the line is written through [`io::stderr`], which is not
+    /// async-signal-safe. The line is written as the level followed by the message, without a
+    /// timestamp or component.
+    #[cfg(not(unix))]
+    pub fn emergency(level: LogLevel, message: &'static str) -> bool {
+        let mut stderr = io::stderr();
+        stderr
+            .write_all(Self::emergency_prefix(level).as_bytes())
+            .and_then(|()| stderr.write_all(message.as_bytes()))
+            .and_then(|()| stderr.write_all(b"\n"))
+            .and_then(|()| stderr.flush())
+            .is_ok()
+    }
+
+    #[cfg(unix)]
+    fn emergency_to_fd(fd: libc::c_int, level: LogLevel, message: &str) -> bool {
+        fn write_fd(fd: libc::c_int, mut bytes: &[u8]) -> bool {
+            while !bytes.is_empty() {
+                // Safety: the pointer and length are taken from a valid byte slice
+                let n = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+                if n <= 0 {
+                    return false;
+                }
+                bytes = &bytes[n as usize..];
+            }
+            true
+        }
+
+        write_fd(fd, Self::emergency_prefix(level).as_bytes())
+            && write_fd(fd, message.as_bytes())
+            && write_fd(fd, b"\n")
+    }
+
+    fn emergency_prefix(level: LogLevel) -> &'static str {
+        match level {
+            LogLevel::Debug => "[DBG] ",
+            LogLevel::Info => "[INF] ",
+            LogLevel::Warning => "[WRN] ",
+            LogLevel::Error => "[ERR] ",
+            LogLevel::Critical => "[CRT] ",
+        }
+    }
+
+    /// Sends a log event with the given trading `identifiers` attached as structured fields.
+    pub fn send_with_identifiers(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: String,
+        identifiers: LogIdentifiers,
+    ) -> Result<u64, LoggerShuttingDown> {
+        let event = LogEvent {
+            timestamp,
+            level,
+            color,
+            component,
+            message,
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: Some(Box::new(identifiers)),
+        };
+        self.send_event(event)
+    }
+
+    /// Sends a batch of pre-built log `events` to the logging thread in a single channel
+    /// operation, returning a flush token for the last event.
+    ///
+    /// The events are written in order and contiguously (not interleaved with events sent
+    /// concurrently), with each event formatted and routed as if sent individually.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if [`Logger::shutdown`] has begun.
+    pub fn log_batch(&mut self, events: Vec<LogEvent>) -> Result<u64, LoggerShuttingDown> {
+        self.state.send_batch(&self.tx, events)
+    }
+
+    fn send_event(&mut self, event: LogEvent) -> Result<u64, LoggerShuttingDown> {
+        self.state.send(&self.tx, event)
+    }
+
+    /// Starts a timer which logs the elapsed duration for `label` when the returned guard is
+    /// dropped, at the configured timer level.
+    #[must_use]
+    pub fn timer(&self, component: String, label: String) -> LogTimer {
+        LogTimer {
+            tx: self.tx.clone(),
+            state: self.state.clone(),
+            level: self.timer_level,
+            component,
+            label,
+            start: self.clock.now(),
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// Blocks until the logging thread is done with all events up to and including the one with
+    /// the given flush `token`, or until the `timeout` elapses.
+    ///
+    /// Returns `true` if within the timeout each of these events was either written to its sinks
+    /// and flushed from the logger's buffers to the operating system (see [`Logger::checkpoint`]
+    /// to also sync log files to disk), or discarded without being written. Events are discarded
+    /// when filtered out by a level, dropped (counted in [`Logger::dropped_count`]), or skipped
+    /// by log files while free disk space is below the `disk_free_floor`. A failed write or flush
+    /// is recorded as the last error rather than holding back later tokens.
+    ///
+    /// With `flush_on_checkpoint`, events written to log files are flushed by the next
+    /// [`Logger::checkpoint`] (see [`LoggerConfig::flush_on_checkpoint`]).
+    pub fn wait_flushed(&self, token: u64, timeout: Duration) -> bool {
+        let flushed_seq = lock(&self.state.flushed_seq);
+        let (flushed_seq, _) = self
+            .state
+            .flushed
+            .wait_timeout_while(flushed_seq, timeout, |seq| seq.contiguous < token)
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        flushed_seq.contiguous >= token
+    }
+
+    /// Returns the path of the main log file currently being written (which changes on each
+    /// rotation), or `None` if not logging to a file.
+    #[must_use]
+    pub fn current_file_path(&self) -> Option<PathBuf> {
+        lock(&self.state.current_file_path).clone()
+    }
+
+    /// Returns the count of events which were dropped rather than logged, such as when the
+    /// channel is full or while paused (events sampled out or rate limited are counted
+    /// separately).
+    #[must_use]
+    pub fn dropped_count(&self) -> u64 {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the count of events discarded by the [`LoggerConfig::sampling`] rules.
+    #[must_use]
+    pub fn sampled_count(&self) -> u64 {
+        self.state.sampled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the count of events discarded by the [`LoggerConfig::rate_limits`] (including the
+    /// default rate limit).
+    #[must_use]
+    pub fn rate_limited_count(&self) -> u64 {
+        self.state.rate_limited.load(Ordering::Relaxed)
+    }
+
+    /// Returns the counts of events logged per level and dropped since the counts were last
+    /// taken, resetting them to zero in the same operation (so no event is counted in two
+    /// intervals or missed between them).
+    ///
+    /// The run totals, such as [`Logger::dropped_count`] and the shutdown summary, are unaffected.
+    pub fn take_and_reset_counts(&self) -> LogCountsSnapshot {
+        std::mem::take(&mut *lock(&self.state.interval_counts))
+    }
+
+    /// Sets whether logged events are captured in memory for retrieval with
+    /// [`Logger::take_captured`] (in addition to being written).
+    ///
+    /// The capture buffer is unbounded, growing with every logged event until taken, so it's
+    /// intended for tests and short diagnostic windows rather than being left enabled.
+    pub fn set_capturing(&self, capturing: bool) {
+        self.state.capturing.store(capturing, Ordering::Relaxed);
+    }
+
+    /// Pauses writing to all sinks, with subsequent events held by the logging thread (up to the
+    /// configured pause capacity) until [`Logger::resume`] is called.
+    pub fn pause(&self) {
+        self.send_command(LoggerCommand::Pause);
+    }
+
+    /// Resumes writing to all sinks, first writing any events held since [`Logger::pause`].
+    pub fn resume(&self) {
+        self.send_command(LoggerCommand::Resume);
+    }
+
+    /// Flushes all log files and syncs them to disk, blocking until every event sent before the
+    /// checkpoint has been handled and is durable.
+    ///
+    /// Events held while paused are not written by a checkpoint.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if flushing or syncing a log file fails, or if the logging
+    /// thread has stopped.
+    pub fn checkpoint(&self) -> io::Result<()> {
+        let (tx, rx) = sync_channel(1);
+        self.send_command(LoggerCommand::Checkpoint(tx));
+        rx.recv().unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Logging thread has stopped",
+            ))
+        })
+    }
+
+    /// Shuts down the logger, blocking until all accepted events have been handled and the
+    /// logging thread has stopped.
+    ///
+    /// Events sent once shutdown has begun are rejected with [`LoggerShuttingDown`]. Calling
+    /// this again once the logger has shut down has no effect.
+    ///
+    /// With [`RateLimitAction::Block`] (the default), the accepted events are still paced by
+    /// their rate limits, so this blocks for as long as their budgets take to allow them all (a
+    /// backlog of `n` events over a limit of `per_second` takes around `n / per_second` seconds).
+    pub fn shutdown(&mut self) {
+        self.state.shutting_down.store(true, Ordering::SeqCst);
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        self.send_command(LoggerCommand::Shutdown);
+        if handle.join().is_err() {
+            self.state
+                .record_error(String::from("Error joining logging thread"));
+        }
+    }
+
+    /// Returns the count of events rejected because the logger was shutting down.
+    #[must_use]
+    pub fn rejected_count(&self) -> u64 {
+        self.state.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Returns the count of events not received from a [`LogStream`] because they exceeded its
+    /// time-to-live or were displaced from its full queue.
+    #[cfg(feature = "log-stream")]
+    #[must_use]
+    pub fn expired_count(&self) -> u64 {
+        self.state.expired.load(Ordering::Relaxed)
+    }
+
+    fn send_command(&self, command: LoggerCommand) {
+        if let Err(SendError(e)) = self.tx.send(command) {
+            self.state
+                .record_error(format!("Error sending log command: {:?}", e));
+        }
+    }
+
+    /// Subscribes to a live stream of logged events, each received as a JSON object string.
+    ///
+    /// Events are streamed after component level filtering and sampling are applied. The stream
+    /// buffers up to `capacity` events (at least one, as an unbuffered stream would drop every
+    /// subscriber not already blocked receiving); a subscriber which falls further behind is
+    /// dropped (its receiver disconnects) rather than stalling the logging thread.
+    #[cfg(feature = "log-stream")]
+    #[must_use]
+    pub fn subscribe(&self, capacity: usize) -> Receiver<String> {
+        let (tx, rx) = sync_channel(capacity.max(1));
+        lock(&self.state.subscribers).push(LogSubscriber::Channel(tx));
+        rx
+    }
+
+    /// Subscribes to a live stream of logged events as for [`Logger::subscribe`], discarding any
+    /// event older than the `ttl` when it's received, so the stream stays fresh when the logging
+    /// thread or the consumer falls behind.
+    ///
+    /// The stream buffers up to `capacity` events (at least one), displacing the oldest rather
+    /// than dropping the subscriber once full. The age of each event is its timestamp compared
+    /// against the [`LoggerConfig::clock`], with discarded and displaced events counted in
+    /// [`Logger::expired_count`] (other sinks still write them).
+    #[cfg(feature = "log-stream")]
+    #[must_use]
+    pub fn subscribe_with_ttl(&self, capacity: usize, ttl: Duration) -> LogStream {
+        let queue = Arc::new(LogStreamQueue {
+            events: Mutex::new(LogStreamEvents {
+                queue: VecDeque::new(),
+                is_closed: self.handle.is_none(),
+            }),
+            available: Condvar::new(),
+            capacity: capacity.max(1),
+            ttl: u64::try_from(ttl.as_nanos()).unwrap_or(u64::MAX),
+            clock: self.clock.clone(),
+            expired: self.state.expired.clone(),
+        });
+        lock(&self.state.subscribers).push(LogSubscriber::Stream(queue.clone()));
+        LogStream { queue }
+    }
+
+    /// Takes the captured events, leaving the capture buffer empty.
+    ///
+    /// Blocks until all events sent so far have been handled by the logging thread, or until
+    /// the [`CAPTURE_FLUSH_TIMEOUT`] elapses.
+    pub fn take_captured(&self) -> Vec<LogEvent> {
+        let sent_seq = self.state.sent_seq.load(Ordering::Relaxed);
+        self.wait_flushed(sent_seq, CAPTURE_FLUSH_TIMEOUT);
+        std::mem::take(&mut *lock(&self.state.captured))
+    }
+
+    /// Returns the events in the in-memory ring buffer with timestamps in the inclusive range
+    /// `from_ns` to `to_ns`, and with at least the given `min_level`, in submission order.
+    ///
+    /// Only events already handled by the logging thread are buffered, and the scan is bounded
+    /// by the configured `buffer_capacity` (so nothing is returned if the buffer is disabled).
+    #[must_use]
+    pub fn query_buffer(
+        &self,
+        from_ns: UnixNanos,
+        to_ns: UnixNanos,
+        min_level: LogLevel,
+    ) -> Vec<LogEvent> {
+        lock(&self.state.buffer)
+            .iter()
+            .filter(|e| e.timestamp >= from_ns && e.timestamp <= to_ns && e.level >= min_level)
+            .cloned()
+            .collect()
+    }
+
+    /// Writes a post-mortem snapshot of the logger state to the file at `path` as JSON.
+    ///
+    /// The snapshot includes the ring buffer of recently logged events, the dropped, sampled and
+    /// rate limited event counts and the last error. This method never panics or blocks on a lock, so it's safe to call from
+    /// a panic hook (the recent events or last error are omitted if their lock is contended).
+    pub fn dump_forensics<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let forensics = LoggerForensics {
+            trader_id: self.trader_id.to_string(),
+            machine_id: self.machine_id.clone(),
+            instance_id: self.instance_id.to_string(),
+            dropped: self.dropped_count(),
+            sampled: self.sampled_count(),
+            rate_limited: self.rate_limited_count(),
+            last_error: try_lock(&self.state.last_error).and_then(|e| e.clone()),
+            recent: try_lock(&self.state.buffer).map(|b| b.iter().cloned().collect()),
+        };
+        let json = serde_json::to_vec_pretty(&forensics)?;
+        let mut file = File::create(path)?;
+        file.write_all(&json)?;
+        file.flush()
+    }
+
+    /// Returns whether an event at the given `level` would be written to the console or a log
+    /// file (including circular files), checking the bypass flag and sink levels only.
+    ///
+    /// Component filters, level remaps, sampling and rate limits are applied later on the
+    /// logging thread, so an event may still be dropped when this returns `true`.
+    #[must_use]
+    pub fn would_log(&self, level: LogLevel) -> bool {
+        !self.is_bypassed
+            && (level >= self.level_stdout
+                || level >= LogLevel::Error
+                || self
+                    .level_file
+                    .is_some_and(|level_file| level >= level_file)
+                || self
+                    .level_circular
+                    .is_some_and(|level_circular| level >= level_circular))
+    }
+
+    /// Sends a log event with the message built by `message`, which is only called if
+    /// [`Logger::would_log`] passes for the `level` (an event rejected because shutdown has
+    /// begun is counted in [`Logger::rejected_count`]).
+    fn send_lazy(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        if self.would_log(level) {
+            let _ = self.send(timestamp, level, color, component, message());
+        }
+    }
+
+    /// Logs a DEBUG event from the `component`, counting it in [`Logger::rejected_count`] if
+    /// shutdown has begun.
+    pub fn debug(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
+        let _ = self.send(timestamp, LogLevel::Debug, color, component, message);
+    }
+
+    /// Logs a INFO event from the `component`, counting it in [`Logger::rejected_count`] if
+    /// shutdown has begun.
+    pub fn info(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
+        let _ = self.send(timestamp, LogLevel::Info, color, component, message);
+    }
+
+    /// Logs a WARNING event from the `component`, counting it in [`Logger::rejected_count`] if
+    /// shutdown has begun.
+    pub fn warn(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
+        let _ = self.send(timestamp, LogLevel::Warning, color, component, message);
+    }
+
+    /// Logs a ERROR event from the `component`, counting it in [`Logger::rejected_count`] if
+    /// shutdown has begun.
+    pub fn error(&mut self, timestamp: u64, color: LogColor, component: String, message: String) {
+        let _ = self.send(timestamp, LogLevel::Error, color, component, message);
+    }
+
+    /// Logs a CRITICAL event from the `component`, counting it in [`Logger::rejected_count`] if
+    /// shutdown has begun.
+    pub fn critical(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: String,
+    ) {
+        let _ = self.send(timestamp, LogLevel::Critical, color, component, message);
+    }
+
+    /// Logs a DEBUG event from the [`LoggerConfig::default_component`], counting it in
+    /// [`Logger::rejected_count`] if shutdown has begun.
+    pub fn debug_default(&mut self, timestamp: u64, color: LogColor, message: String) {
+        let component = self.default_component.to_string();
+        let _ = self.send(timestamp, LogLevel::Debug, color, component, message);
+    }
+
+    /// Logs a INFO event from the [`LoggerConfig::default_component`], counting it in
+    /// [`Logger::rejected_count`] if shutdown has begun.
+    pub fn info_default(&mut self, timestamp: u64, color: LogColor, message: String) {
+        let component = self.default_component.to_string();
+        let _ = self.send(timestamp, LogLevel::Info, color, component, message);
+    }
+
+    /// Logs a WARNING event from the [`LoggerConfig::default_component`], counting it in
+    /// [`Logger::rejected_count`] if shutdown has begun.
+    pub fn warn_default(&mut self, timestamp: u64, color: LogColor, message: String) {
+        let component = self.default_component.to_string();
+        let _ = self.send(timestamp, LogLevel::Warning, color, component, message);
+    }
+
+    /// Logs a ERROR event from the [`LoggerConfig::default_component`], counting it in
+    /// [`Logger::rejected_count`] if shutdown has begun.
+    pub fn error_default(&mut self, timestamp: u64, color: LogColor, message: String) {
+        let component = self.default_component.to_string();
+        let _ = self.send(timestamp, LogLevel::Error, color, component, message);
+    }
+
+    /// Logs a CRITICAL event from the [`LoggerConfig::default_component`], counting it in
+    /// [`Logger::rejected_count`] if shutdown has begun.
+    pub fn critical_default(&mut self, timestamp: u64, color: LogColor, message: String) {
+        let component = self.default_component.to_string();
+        let _ = self.send(timestamp, LogLevel::Critical, color, component, message);
+    }
+
+    /// Logs a DEBUG event with the message built by `message` only if it would be logged,
+    /// counting it in [`Logger::rejected_count`] if shutdown has begun.
+    pub fn debug_lazy(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        self.send_lazy(timestamp, LogLevel::Debug, color, component, message);
+    }
+
+    /// Logs a INFO event with the message built by `message` only if it would be logged,
+    /// counting it in [`Logger::rejected_count`] if shutdown has begun.
+    pub fn info_lazy(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        self.send_lazy(timestamp, LogLevel::Info, color, component, message);
+    }
+
+    /// Logs a WARNING event with the message built by `message` only if it would be logged,
+    /// counting it in [`Logger::rejected_count`] if shutdown has begun.
+    pub fn warn_lazy(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        self.send_lazy(timestamp, LogLevel::Warning, color, component, message);
+    }
+
+    /// Logs a ERROR event with the message built by `message` only if it would be logged,
+    /// counting it in [`Logger::rejected_count`] if shutdown has begun.
+    pub fn error_lazy(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        self.send_lazy(timestamp, LogLevel::Error, color, component, message);
+    }
+
+    /// Logs a CRITICAL event with the message built by `message` only if it would be logged,
+    /// counting it in [`Logger::rejected_count`] if shutdown has begun.
+    pub fn critical_lazy(
+        &mut self,
+        timestamp: u64,
+        color: LogColor,
+        component: String,
+        message: impl FnOnce() -> String,
+    ) {
+        self.send_lazy(timestamp, LogLevel::Critical, color, component, message);
+    }
+}
+
+impl Drop for Logger {
+    /// Shuts down the logger if it hasn't been already, so that its files are flushed and their
+    /// locks released.
+    ///
+    /// This blocks until the logging thread has stopped, which with [`RateLimitAction::Block`]
+    /// includes waiting out the rate limits of any backlog (see [`Logger::shutdown`]).
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Provides an RAII timer which logs the duration elapsed since it was started when dropped.
+///
+/// The current time is read from the [`LoggerConfig::clock`].
+pub struct LogTimer {
+    tx: CommandSender,
+    state: Arc<LoggerState>,
+    level: LogLevel,
+    component: String,
+    label: String,
+    start: UnixNanos,
+    clock: LogClock,
+}
+
+impl Drop for LogTimer {
+    fn drop(&mut self) {
+        let now = self.clock.now();
+        let elapsed = format_duration(now.saturating_sub(self.start));
+        let event = LogEvent {
+            timestamp: now,
+            level: self.level,
+            color: LogColor::Normal,
+            component: std::mem::take(&mut self.component),
+            message: format!("{} took {elapsed}", self.label),
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        };
+        // Rejected events are counted by the logger, so there is nothing further to do here
+        let _ = self.state.send(&self.tx, event);
+    }
+}
+
+fn unix_nanos_now() -> UnixNanos {
+    duration_since_unix_epoch().as_nanos() as UnixNanos
+}
+
+/// Returns a human-readable string for the given duration in nanoseconds, using the largest of
+/// microseconds, milliseconds or seconds which keeps the value at or above one.
+fn format_duration(nanos: u64) -> String {
+    if nanos < 1_000_000 {
+        format!("{:.3}µs", nanos as f64 / 1_000.0)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.3}ms", nanos as f64 / 1_000_000.0)
+    } else {
+        format!("{:.3}s", nanos as f64 / 1_000_000_000.0)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Stubs
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(any(test, feature = "stubs"))]
+pub mod stubs {
+    use std::{
+        io::{self, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use nautilus_core::uuid::UUID4;
+    use nautilus_model::identifiers::trader_id::TraderId;
+    use rstest::fixture;
+
+    use crate::{
+        enums::{LogColor, LogLevel},
+        logging::{LogEvent, LogWriter, Logger, LoggerConfig},
+    };
+
+    /// Provides a writer which sleeps for a delay on every write, simulating a slow sink.
+    #[derive(Clone)]
+    pub struct SlowWriter {
+        delay: Duration,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl SlowWriter {
+        pub fn new(delay: Duration) -> Self {
+            Self {
+                delay,
+                written: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        /// Returns the lines written so far.
+        pub fn lines(&self) -> Vec<String> {
+            let written = self.written.lock().unwrap();
+            String::from_utf8_lossy(&written)
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    impl Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            thread::sleep(self.delay);
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns a log event at a fixed timestamp, to be customized with struct update syntax.
+    pub fn log_event(level: LogLevel, color: LogColor, component: &str, message: &str) -> LogEvent {
+        LogEvent::new(
+            1_650_000_000_000_000,
+            level,
+            color,
+            String::from(component),
+            String::from(message),
+        )
+    }
+
+    /// Returns a console logger at the INFO level with the given `config`.
+    pub fn logger_with_config(config: LoggerConfig) -> Logger {
+        Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            config,
+        )
+    }
+
+    /// Returns a logger writing to the console and the `test.log` file in the `directory`, both
+    /// at the INFO level, with the given `config`.
+    pub fn file_logger_with_config(directory: &Path, config: LoggerConfig) -> Logger {
+        Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            Some(LogLevel::Info),
+            Some(directory.to_str().unwrap().to_string()),
+            Some("test".to_string()),
+            None,
+            None,
+            false,
+            config,
+        )
+    }
+
+    /// Returns a logger writing to the console and a log file in the `directory` with the
+    /// default (rotated) file name, both at the INFO level, with the given `config`.
+    pub fn rotating_file_logger_with_config(directory: &Path, config: LoggerConfig) -> Logger {
+        Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            Some(LogLevel::Info),
+            Some(directory.to_str().unwrap().to_string()),
+            None,
+            None,
+            None,
+            false,
+            config,
+        )
+    }
+
+    /// Returns a console logger with the given `config`, writing to a [`SlowWriter`] which
+    /// sleeps for `delay` on every write.
+    pub fn slow_logger(delay: Duration, config: LoggerConfig) -> (Logger, SlowWriter) {
+        let writer = SlowWriter::new(delay);
+        let logger = logger_with_config(LoggerConfig {
+            console_writer: Some(LogWriter::new(writer.clone())),
+            ..config
+        });
+        (logger, writer)
+    }
+
+    #[fixture]
+    pub fn logger() -> Logger {
+        logger_with_config(LoggerConfig::default())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Tests
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nautilus_core::uuid::UUID4;
+    use nautilus_model::identifiers::trader_id::TraderId;
+    use rstest::*;
+    use tempfile::tempdir;
+
+    use super::{stubs::*, *};
+    use crate::testing::wait_until;
+
+    #[rstest]
+    fn log_message_serialization() {
+        let log_message = LogEvent {
+            timestamp: 1_000_000_000,
+            level: LogLevel::Info,
+            color: LogColor::Normal,
+            component: "Portfolio".to_string(),
+            message: "This is a log message".to_string(),
+            sample_key: None,
+            rate_domain: None,
+            seq: 0,
+            identifiers: None,
+        };
+
+        let serialized_json = serde_json::to_string(&log_message).unwrap();
+        let deserialized_value: Value = serde_json::from_str(&serialized_json).unwrap();
+
+        assert_eq!(deserialized_value["timestamp"], 1_000_000_000);
+        assert_eq!(deserialized_value["level"], "INFO");
+        assert_eq!(deserialized_value["component"], "Portfolio");
+        assert_eq!(deserialized_value["message"], "This is a log message");
+    }
+
+    #[rstest]
+    fn test_new_logger(logger: Logger) {
+        assert_eq!(logger.trader_id, TraderId::from("TRADER-001"));
+        assert_eq!(logger.level_stdout, LogLevel::Info);
+        assert_eq!(logger.level_file, None);
+        assert!(!logger.is_bypassed);
+    }
+
+    #[rstest]
+    fn test_logger_debug(mut logger: Logger) {
+        logger.debug(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test debug message."),
+        );
+    }
+
+    #[rstest]
+    fn test_logger_info(mut logger: Logger) {
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test info message."),
+        );
+    }
+
+    #[rstest]
+    fn test_logger_error(mut logger: Logger) {
+        logger.error(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test error message."),
+        );
+    }
+
+    #[rstest]
+    fn test_logger_critical(mut logger: Logger) {
+        logger.critical(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test critical message."),
+        );
+    }
+
+    #[rstest]
+    fn test_logging_to_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let mut logger = Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            Some(LogLevel::Debug),
+            Some(temp_dir.path().to_str().unwrap().to_string()),
+            None,
+            None,
+            None,
+            false,
+            LoggerConfig::default(),
+        );
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+
+        let mut log_contents = String::new();
+
+        wait_until(
+            || {
+                let log_file_exists = std::fs::read_dir(&temp_dir)
+                    .expect("Failed to read directory")
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .next()
+                    .is_some();
+
+                log_file_exists
+            },
+            Duration::from_secs(2),
+        );
+
+        wait_until(
+            || {
+                let log_file_path = std::fs::read_dir(&temp_dir)
+                    .expect("Failed to read directory")
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .next()
+                    .expect("No files found in directory")
+                    .path();
+                log_contents =
+                    std::fs::read_to_string(&log_file_path).expect("Error while reading log file");
+                !log_contents.is_empty()
+            },
+            Duration::from_secs(2),
+        );
+
+        assert_eq!(
+            log_contents,
+            "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: This is a test.\n"
+        );
+    }
+
+    #[rstest]
+    fn test_log_component_level_filtering() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let mut logger = Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            Some(LogLevel::Debug),
+            Some(temp_dir.path().to_str().unwrap().to_string()),
+            None,
+            None,
+            Some(HashMap::from_iter(std::iter::once((
+                String::from("RiskEngine"),
+                Value::from("ERROR"), // <-- This should be filtered
+            )))),
+            false,
+            LoggerConfig::default(),
+        );
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+
+        wait_until(
+            || {
+                if let Some(log_file) = std::fs::read_dir(&temp_dir)
+                    .expect("Failed to read directory")
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .next()
+                {
+                    let log_file_path = log_file.path();
+                    let log_contents = std::fs::read_to_string(&log_file_path)
+                        .expect("Error while reading log file");
+                    !log_contents.contains("RiskEngine")
+                } else {
+                    false
+                }
+            },
+            Duration::from_secs(3),
+        );
+
+        assert!(
+            std::fs::read_dir(&temp_dir)
+                .expect("Failed to read directory")
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_file())
+                .next()
+                .is_some(),
+            "Log file exists"
+        );
+    }
+
+    #[rstest]
+    fn test_logging_to_file_in_json_format() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let mut logger = Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Info,
+            Some(LogLevel::Debug),
+            Some(temp_dir.path().to_str().unwrap().to_string()),
+            None,
+            Some("json".to_string()),
+            None,
+            false,
+            LoggerConfig::default(),
+        );
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+
+        let mut log_contents = String::new();
+
+        wait_until(
+            || {
+                if let Some(log_file) = std::fs::read_dir(&temp_dir)
+                    .expect("Failed to read directory")
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .next()
+                {
+                    let log_file_path = log_file.path();
+                    log_contents = std::fs::read_to_string(&log_file_path)
+                        .expect("Error while reading log file");
+                    !log_contents.is_empty()
+                } else {
+                    false
+                }
+            },
+            Duration::from_secs(2),
+        );
+
+        assert_eq!(
+        log_contents,
+        "{\"timestamp\":1650000000000000,\"level\":\"INFO\",\"color\":\"Normal\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}\n"
+    );
+    }
+
+    fn sample_event(component: &str, message: &str, sample_key: Option<&str>) -> LogEvent {
+        LogEvent {
+            sample_key: sample_key.map(String::from),
+            ..log_event(LogLevel::Info, LogColor::Normal, component, message)
+        }
+    }
+
+    #[rstest]
+    fn test_sampler_logs_first_n_then_every_m() {
+        let mut sampler = LogSampler::new(
+            HashMap::from([(String::from("MatchingEngine"), LogSampling::new(3, 5))]),
+            None,
+            100,
+        );
+        let event = sample_event("MatchingEngine", "Processing order", None);
+
+        let logged: Vec<u64> = (1..=20).filter(|_| sampler.should_log(&event)).collect();
+
+        assert_eq!(logged, vec![1, 2, 3, 8, 13, 18]);
+    }
+
+    #[rstest]
+    fn test_sampler_ignores_components_without_rule() {
+        let mut sampler = LogSampler::new(
+            HashMap::from([(String::from("MatchingEngine"), LogSampling::new(1, 0))]),
+            None,
+            100,
+        );
+        let event = sample_event("RiskEngine", "Processing order", None);
+
+        assert!((0..10).all(|_| sampler.should_log(&event)));
+    }
+
+    #[rstest]
+    fn test_sampler_uses_sample_key_and_default_rule() {
+        let mut sampler = LogSampler::new(HashMap::new(), Some(LogSampling::new(1, 0)), 100);
+
+        assert!(sampler.should_log(&sample_event("DataEngine", "Tick 1", Some("tick"))));
+        assert!(!sampler.should_log(&sample_event("DataEngine", "Tick 2", Some("tick"))));
+        assert!(sampler.should_log(&sample_event("DataEngine", "Bar 1", Some("bar"))));
+    }
+
+    #[rstest]
+    fn test_sampler_key_cache_is_bounded() {
+        let mut sampler = LogSampler::new(HashMap::new(), Some(LogSampling::new(1, 0)), 2);
+
+        assert!(sampler.should_log(&sample_event("DataEngine", "A", None)));
+        assert!(sampler.should_log(&sample_event("DataEngine", "B", None)));
+        assert!(sampler.should_log(&sample_event("DataEngine", "C", None)));
+        assert_eq!(sampler.counts.len(), 2);
+        // Key "A" was evicted so it starts counting again
+        assert!(sampler.should_log(&sample_event("DataEngine", "A", None)));
+    }
+
+    #[rstest]
+    fn test_rate_limiter_throttles_each_domain_separately() {
+        let mut rate_limiter = LogRateLimiter::new(
+            HashMap::from([(String::from("md"), RateLimit::new(1.0, 2))]),
+            Some(RateLimit::new(1.0, 3)),
+            RateLimitAction::Drop,
+            Duration::from_secs(10),
+            LogClock::system(),
+        );
+        let mut md_event = sample_event("DataEngine", "Tick", None);
+        md_event.rate_domain = Some(String::from("md"));
+        let exec_event = sample_event("ExecEngine", "Order", None);
+
+        let md_logged = (0..10)
+            .filter(|_| rate_limiter.should_log(&md_event, 1_000_000_000))
+            .count();
+        let exec_logged = (0..10)
+            .filter(|_| rate_limiter.should_log(&exec_event, 1_000_000_000))
+            .count();
+        assert_eq!(md_logged, 2);
+        assert_eq!(exec_logged, 3);
+
+        // Budget refills at one event per second
+        assert!(rate_limiter.should_log(&md_event, 2_000_000_000));
+        assert!(!rate_limiter.should_log(&md_event, 2_500_000_000));
+    }
+
+    #[rstest]
+    fn test_rate_limiter_without_limits_logs_all() {
+        let mut rate_limiter = LogRateLimiter::new(
+            HashMap::new(),
+            None,
+            RateLimitAction::Drop,
+            Duration::from_secs(10),
+            LogClock::system(),
+        );
+        let mut event = sample_event("DataEngine", "Tick", None);
+        event.rate_domain = Some(String::from("md"));
+
+        assert!((0..100).all(|_| rate_limiter.should_log(&event, 1_000_000_000)));
+    }
+
+    #[rstest]
+    fn test_rate_limiter_summarizes_suppressed_events() {
+        let mut rate_limiter = LogRateLimiter::new(
+            HashMap::new(),
+            Some(RateLimit::new(1.0, 1)),
+            RateLimitAction::Summarize,
+            Duration::from_secs(10),
+            LogClock::system(),
+        );
+        let event = sample_event("DataEngine", "Tick", None);
+
+        let logged = (0..5)
+            .filter(|_| rate_limiter.should_log(&event, 1_000_000_000))
+            .count();
+
+        assert_eq!(logged, 1);
+        assert_eq!(rate_limiter.take_summary(5_000_000_000), None);
+        assert_eq!(rate_limiter.take_summary(11_000_000_000), Some(4));
+        assert_eq!(rate_limiter.take_summary(30_000_000_000), None);
+    }
+
+    #[rstest]
+    fn test_rate_limiter_block_waits_for_budget() {
+        let mut rate_limiter = LogRateLimiter::new(
+            HashMap::new(),
+            Some(RateLimit::new(1_000.0, 1)),
+            RateLimitAction::Block,
+            Duration::from_secs(10),
+            LogClock::system(),
+        );
+        let event = sample_event("DataEngine", "Tick", None);
+
+        assert!((0..5).all(|_| rate_limiter.should_log(&event, 1_000_000_000)));
+        assert_eq!(rate_limiter.take_summary(100_000_000_000), None);
+    }
+
+    fn rate_limited_config(summary_interval: Duration) -> LoggerConfig {
+        LoggerConfig {
+            rate_limit_default: Some(RateLimit::new(0.001, 1)),
+            rate_limit_action: RateLimitAction::Summarize,
+            rate_limit_summary_interval: summary_interval,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn test_rate_limit_summary_written_once_traffic_stops() {
+        let (mut logger, writer) = slow_logger(
+            Duration::ZERO,
+            rate_limited_config(Duration::from_millis(50)),
+        );
+        for i in 0..3 {
+            log_and_wait(&mut logger, &format!("Event {i}."));
+        }
+
+        wait_until(
+            || {
+                writer
+                    .lines()
+                    .iter()
+                    .any(|line| line.contains("2 messages suppressed by rate limit"))
+            },
+            Duration::from_secs(2),
+        );
+        assert_eq!(writer.lines().len(), 2);
+        assert!(writer.lines()[0].contains("Event 0."));
+    }
+
+    #[rstest]
+    fn test_rate_limit_summary_written_at_shutdown() {
+        let (mut logger, writer) = slow_logger(
+            Duration::ZERO,
+            rate_limited_config(Duration::from_secs(3600)),
+        );
+        for i in 0..3 {
+            log_and_wait(&mut logger, &format!("Event {i}."));
+        }
+        assert_eq!(writer.lines().len(), 1);
+
+        logger.shutdown();
+
+        let lines = writer.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("2 messages suppressed by rate limit"));
+        assert_eq!(logger.rate_limited_count(), 2);
+        assert_eq!(logger.dropped_count(), 0);
+    }
+
+    #[rstest]
+    fn test_rate_limit_action_defaults_to_block() {
+        assert_eq!(RateLimitAction::default(), RateLimitAction::Block);
+    }
+
+    #[rstest]
+    fn test_invalid_rate_limits_recorded_and_ignored() {
+        let (mut logger, writer) = slow_logger(
+            Duration::ZERO,
+            LoggerConfig {
+                rate_limits: HashMap::from([(String::from("md"), RateLimit::new(f64::NAN, 1))]),
+                rate_limit_default: Some(RateLimit::new(0.0, 1)),
+                ..Default::default()
+            },
+        );
+        for i in 0..3 {
+            log_and_wait(&mut logger, &format!("Event {i}."));
+        }
+
+        let last_error = lock(&logger.state.last_error).clone().unwrap();
+        assert_eq!(writer.lines().len(), 3);
+        assert_eq!(logger.rate_limited_count(), 0);
+        assert!(last_error.contains("invalid default rate limit"));
+    }
+
+    #[rstest]
+    fn test_dump_forensics() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let config = LoggerConfig {
+            sampling: HashMap::from([(String::from("RiskEngine"), LogSampling::new(2, 0))]),
+            buffer_capacity: 1,
+            ..Default::default()
+        };
+        let mut logger = logger_with_config(config);
+
+        for i in 0..3 {
+            logger.debug(
+                1_650_000_000_000_000 + i,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("This is a test."),
+            );
+        }
+
+        wait_until(|| logger.sampled_count() == 1, Duration::from_secs(2));
+
+        let path = temp_dir.path().join("forensics.json");
+        logger.dump_forensics(&path).unwrap();
+
+        let contents = std::fs::read_to_string(&path).expect("Error while reading forensics file");
+        let forensics: Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(forensics["trader_id"], "TRADER-001");
+        assert_eq!(forensics["dropped"], 0);
+        assert_eq!(forensics["sampled"], 1);
+        assert_eq!(forensics["rate_limited"], 0);
+        assert_eq!(forensics["last_error"], Value::Null);
+        assert_eq!(forensics["recent"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            forensics["recent"][0]["timestamp"],
+            1_650_000_000_000_001_u64
+        );
+    }
+
+    #[rstest]
+    fn test_format_log_line_with_glyph() {
+        let event = log_event(
+            LogLevel::Warning,
+            LogColor::Yellow,
+            "RiskEngine",
+            "This is a test.",
+        );
+
+        let formatter = LineFormatter::new("TRADER-001", &LoggerConfig::default());
+
+        let line = formatter.format_file(
+            &event,
+            &LogTemplate::new("{ts} {glyph}[{level}] {trader_id}.{component}: {message}\n"),
+            false,
+            "⚠ ",
+        );
+
+        assert_eq!(
+            line,
+            "1970-01-20T02:20:00.000000000Z ⚠ [WRN] TRADER-001.RiskEngine: This is a test.\n"
+        );
+    }
+
+    #[rstest]
+    fn test_format_log_line_json_with_renamed_fields() {
+        let event = log_event(
+            LogLevel::Info,
+            LogColor::Normal,
+            "RiskEngine",
+            "This is a test.",
+        );
+        let renames = HashMap::from([
+            (String::from("timestamp"), String::from("ts")),
+            (String::from("level"), String::from("lvl")),
+        ]);
+        let config = LoggerConfig {
+            json_field_names: JsonFieldNames::new(renames).unwrap(),
+            ..Default::default()
+        };
+        let formatter = LineFormatter::new("TRADER-001", &config);
+
+        let line = formatter.format_file(&event, &LogTemplate::new(""), true, "");
+
+        assert_eq!(
+            line,
+            "{\"ts\":1650000000000000,\"lvl\":\"INFO\",\"color\":\"Normal\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}\n"
+        );
+    }
+
+    #[rstest]
+    #[case("color", "level")]
+    #[case("lvl", "lvl")]
+    #[case("time", "message")]
+    fn test_json_field_names_rejects_duplicate_targets(
+        #[case] timestamp: &str,
+        #[case] level: &str,
+    ) {
+        let renames = HashMap::from([
+            (String::from("timestamp"), String::from(timestamp)),
+            (String::from("level"), String::from(level)),
+        ]);
+
+        assert!(JsonFieldNames::new(renames).is_err());
+    }
+
+    #[rstest]
+    #[case("event_id")]
+    #[case("run_id")]
+    #[case("instrument_id")]
+    #[case("client_order_id")]
+    #[case("venue_order_id")]
+    #[case("position_id")]
+    #[case("trade_id")]
+    fn test_json_field_names_rejects_optional_field_targets(#[case] target: &str) {
+        let renames = HashMap::from([(String::from("message"), String::from(target))]);
+
+        assert!(JsonFieldNames::new(renames).is_err());
+    }
+
+    #[rstest]
+    fn test_json_field_names_rejects_unknown_field() {
+        let renames = HashMap::from([(String::from("msg"), String::from("text"))]);
+
+        assert!(JsonFieldNames::new(renames).is_err());
+    }
+
+    #[rstest]
+    #[case("TRADER-001_2023-09-01_5d3b5e5e-0000-4000-8000-000000000001.log", true)]
+    #[case(
+        "TRADER-001_2023-09-01_5d3b5e5e-0000-4000-8000-000000000001.json",
+        true
+    )]
+    #[case("TRADER-001_2023-09-01_.log", false)]
+    #[case("TRADER-001_2023-9-1_5d3b5e5e.log", false)]
+    #[case("TRADER-002_2023-09-01_5d3b5e5e.log", false)]
+    #[case("TRADER-001_2023-09-01_5d3b5e5e.txt", false)]
+    #[case("my_trading.log", false)]
+    fn test_is_rotated_log_file(#[case] file_name: &str, #[case] expected: bool) {
+        assert_eq!(
+            Logger::is_rotated_log_file(file_name, "TRADER-001"),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("logs/trades.json", "logs/trades_2024-01-02.json")]
+    #[case("logs/trades", "logs/trades_2024-01-02")]
+    fn test_dated_log_file_path(#[case] path: &str, #[case] expected: &str) {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+
+        assert_eq!(
+            Logger::dated_log_file_path(Path::new(path), date),
+            PathBuf::from(expected)
+        );
+    }
+
+    #[rstest]
+    fn test_file_retention_removes_excess_rotated_files() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let rotated_files = [
+            "TRADER-001_2023-09-01_5d3b5e5e-0000-4000-8000-000000000001.log",
+            "TRADER-001_2023-09-02_5d3b5e5e-0000-4000-8000-000000000002.log",
+            "TRADER-001_2023-09-03_5d3b5e5e-0000-4000-8000-000000000003.log",
+        ];
+        for file_name in rotated_files {
+            std::fs::write(temp_dir.path().join(file_name), "").unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+        std::fs::write(temp_dir.path().join("unrelated.log"), "").unwrap();
+
+        let _logger = rotating_file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_retention: Some(FileRetention {
+                    max_files: Some(1),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let file_exists = |name: &str| temp_dir.path().join(name).exists();
+        wait_until(
+            || !file_exists(rotated_files[0]) && !file_exists(rotated_files[1]),
+            Duration::from_secs(2),
+        );
+
+        assert!(file_exists(rotated_files[2]));
+        assert!(file_exists("unrelated.log"));
+        assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 3); // Includes current file
+    }
+
+    #[rstest]
+    fn test_file_retention_skips_files_in_use_by_another_logger() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let closed_file = "TRADER-001_2023-09-01_5d3b5e5e-0000-4000-8000-000000000001.log";
+        std::fs::write(temp_dir.path().join(closed_file), "").unwrap();
+        let config = LoggerConfig {
+            file_retention: Some(FileRetention {
+                max_files: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut logger1 =
+            rotating_file_logger_with_config(temp_dir.path(), LoggerConfig::default());
+        log_and_wait(&mut logger1, "First logger.");
+        let in_use_path = logger1.current_file_path().unwrap();
+
+        let mut logger2 = rotating_file_logger_with_config(temp_dir.path(), config);
+        log_and_wait(&mut logger2, "Second logger.");
+
+        assert!(!temp_dir.path().join(closed_file).exists());
+        assert!(in_use_path.exists());
+        assert!(std::fs::read_to_string(&in_use_path)
+            .unwrap()
+            .ends_with("First logger.\n"));
+    }
+
+    #[rstest]
+    fn test_wait_flushed() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let mut logger = file_logger_with_config(temp_dir.path(), LoggerConfig::default());
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("First message."),
+        );
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("Second message."),
+            )
+            .unwrap();
+
+        assert_eq!(token, 2);
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log"))
+            .expect("Error while reading log file");
+        assert_eq!(log_contents.lines().count(), 2);
+        assert!(log_contents.ends_with("TRADER-001.RiskEngine: Second message.\n"));
+    }
+
+    #[rstest]
+    fn test_wait_flushed_times_out_for_unsent_token(logger: Logger) {
+        assert!(!logger.wait_flushed(1, Duration::from_millis(10)));
+    }
+
+    #[rstest]
+    fn test_flushed_seq_advances_only_when_contiguous() {
+        let mut flushed_seq = FlushedSeq::default();
+
+        flushed_seq.mark(2);
+        flushed_seq.mark(4);
+        assert_eq!(flushed_seq.contiguous, 0);
+
+        flushed_seq.mark(1);
+        assert_eq!(flushed_seq.contiguous, 2);
+
+        flushed_seq.mark(3);
+        assert_eq!(flushed_seq.contiguous, 4);
+        assert!(flushed_seq.pending.is_empty());
+
+        flushed_seq.mark(4);
+        assert_eq!(flushed_seq.contiguous, 4);
+        assert!(flushed_seq.pending.is_empty());
+    }
+
+    #[rstest]
+    fn test_take_captured() {
+        let mut logger = logger_with_config(LoggerConfig {
+            capture: true,
+            ..Default::default()
+        });
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+        logger.warn(
+            1_650_000_000_000_000,
+            LogColor::Yellow,
+            String::from("RiskEngine"),
+            String::from("This is a warning."),
+        );
+
+        let captured = logger.take_captured();
+
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].level, LogLevel::Info);
+        assert_eq!(captured[1].level, LogLevel::Warning);
+        assert_eq!(captured[1].message, "This is a warning.");
+        assert!(logger.take_captured().is_empty());
+
+        logger.set_capturing(false);
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is not captured."),
+        );
+
+        assert!(logger.take_captured().is_empty());
+    }
+
+    #[rstest]
+    fn test_logging_to_file_with_component_separator() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                component_separator: String::from("::"),
+                ..Default::default()
+            },
+        );
+
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("This is a test."),
+            )
+            .unwrap();
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log"))
+            .expect("Error while reading log file");
+        assert_eq!(
+            log_contents,
+            "1970-01-20T02:20:00.000000000Z [INF] TRADER-001::RiskEngine: This is a test.\n"
+        );
+    }
+
+    #[rstest]
+    #[case(0, "0.000µs")]
+    #[case(1_500, "1.500µs")]
+    #[case(999_999, "999.999µs")]
+    #[case(1_000_000, "1.000ms")]
+    #[case(250_500_000, "250.500ms")]
+    #[case(3_000_000_000, "3.000s")]
+    fn test_format_duration(#[case] nanos: u64, #[case] expected: &str) {
+        assert_eq!(format_duration(nanos), expected);
+    }
+
+    #[rstest]
+    fn test_timer_logs_elapsed_duration_on_drop() {
+        let now = Arc::new(AtomicU64::new(1_000_000_000));
+        let clock_now = now.clone();
+        let logger = logger_with_config(LoggerConfig {
+            capture: true,
+            timer_level: LogLevel::Info,
+            clock: LogClock::new(move || clock_now.load(Ordering::Relaxed)),
+            ..Default::default()
+        });
+
+        let timer = logger.timer(String::from("DataEngine"), String::from("Loading bars"));
+        now.store(1_002_500_000, Ordering::Relaxed);
+        drop(timer);
+
+        let captured = logger.take_captured();
+
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].timestamp, 1_002_500_000);
+        assert_eq!(captured[0].level, LogLevel::Info);
+        assert_eq!(captured[0].component, "DataEngine");
+        assert_eq!(captured[0].message, "Loading bars took 2.500ms");
+    }
+
+    #[rstest]
+    fn test_logging_to_multiple_file_outputs() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("machine").join("test.jsonl");
+
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(json_path.clone(), LogFileFormat::Json)],
+                ..Default::default()
+            },
+        );
+
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("This is a test."),
+            )
+            .unwrap();
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log"))
+            .expect("Error while reading log file");
+        let json_contents =
+            std::fs::read_to_string(json_path).expect("Error while reading log file");
+        assert_eq!(
+            log_contents,
+            "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: This is a test.\n"
+        );
+        assert_eq!(
+            json_contents,
+            "{\"timestamp\":1650000000000000,\"level\":\"INFO\",\"color\":\"Normal\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}\n"
+        );
+    }
+
+    fn file_logger(temp_dir: &Path, file_conflict: LogFileConflict) -> Logger {
+        file_logger_with_config(
+            temp_dir,
+            LoggerConfig {
+                file_conflict,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn log_and_wait(logger: &mut Logger, message: &str) {
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from(message),
+            )
+            .unwrap();
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+    }
+
+    #[rstest]
+    fn test_file_conflict_suffixes_path_for_second_logger() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger1 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger1, "First logger.");
+        let mut logger2 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger2, "Second logger.");
+
+        let log_contents1 = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let log_contents2 = std::fs::read_to_string(temp_dir.path().join("test_1.log")).unwrap();
+        assert!(log_contents1.ends_with("First logger.\n"));
+        assert!(log_contents2.ends_with("Second logger.\n"));
+        assert_eq!(log_contents1.lines().count(), 1);
+        assert_eq!(log_contents2.lines().count(), 1);
+    }
+
+    #[rstest]
+    fn test_current_file_path() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger1 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger1, "First logger.");
+        let mut logger2 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger2, "Second logger.");
+
+        assert_eq!(
+            logger1.current_file_path(),
+            Some(temp_dir.path().join("test.log"))
+        );
+        assert_eq!(
+            logger2.current_file_path(),
+            Some(temp_dir.path().join("test_1.log"))
+        );
+    }
+
+    #[rstest]
+    fn test_current_file_path_when_not_logging_to_file(mut logger: Logger) {
+        log_and_wait(&mut logger, "This is a test.");
+
+        assert_eq!(logger.current_file_path(), None);
+    }
+
+    #[rstest]
+    fn test_file_conflict_error_does_not_write_for_second_logger() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger1 = file_logger(temp_dir.path(), LogFileConflict::Error);
+        log_and_wait(&mut logger1, "First logger.");
+        let mut logger2 = file_logger(temp_dir.path(), LogFileConflict::Error);
+        log_and_wait(&mut logger2, "Second logger.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let last_error = lock(&logger2.state.last_error).clone().unwrap();
+        assert!(log_contents.ends_with("First logger.\n"));
+        assert_eq!(log_contents.lines().count(), 1);
+        assert!(!temp_dir.path().join("test_1.log").exists());
+        assert!(last_error.contains("already in use by another logger"));
+    }
+
+    #[rstest]
+    fn test_file_lock_released_when_logger_dropped() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger1 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger1, "First logger.");
+        drop(logger1);
+        let mut logger2 = file_logger(temp_dir.path(), LogFileConflict::Suffix);
+        log_and_wait(&mut logger2, "Second logger.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let lines: Vec<&str> = log_contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("First logger."));
+        assert!(lines[1].ends_with("Second logger."));
+        assert!(!temp_dir.path().join("test_1.log").exists());
+    }
+
+    #[rstest]
+    fn test_file_open_error_recorded_without_panicking() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let directory_path = temp_dir.path().join("output");
+        std::fs::create_dir(&directory_path).unwrap();
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(
+                    directory_path.clone(),
+                    LogFileFormat::Plain,
+                )],
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let last_error = lock(&logger.state.last_error).clone().unwrap();
+        assert!(log_contents.ends_with("This is a test.\n"));
+        assert!(last_error.contains("Error creating log file"));
+        assert!(directory_path.is_dir());
+    }
+
+    #[rstest]
+    fn test_file_output_directory_error_recorded_without_panicking() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let file_path = temp_dir.path().join("output");
+        std::fs::write(&file_path, "").unwrap();
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![
+                    LogFileOutput::new(file_path.join("test.json"), LogFileFormat::Json),
+                    LogFileOutput::new(temp_dir.path().join("test.jsonl"), LogFileFormat::Json),
+                ],
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let json_contents = std::fs::read_to_string(temp_dir.path().join("test.jsonl")).unwrap();
+        let last_error = lock(&logger.state.last_error).clone().unwrap();
+        assert!(log_contents.ends_with("This is a test.\n"));
+        assert!(json_contents.contains("This is a test."));
+        assert!(last_error.contains("Error creating directories for log file"));
+    }
+
+    #[rstest]
+    #[case(LogFileFormat::Plain, "# version: 1.0.0\n# config_hash: abc\"123\n")]
+    #[case(
+        LogFileFormat::Json,
+        "{\"header\":{\"version\":\"1.0.0\",\"config_hash\":\"abc\\\"123\"}}\n"
+    )]
+    fn test_file_header_written_once_at_top_of_file(
+        #[case] format: LogFileFormat,
+        #[case] expected: &str,
+    ) {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("header.log");
+        let config = LoggerConfig {
+            file_outputs: vec![LogFileOutput::new(path.clone(), format)],
+            file_header: vec![
+                (String::from("version"), String::from("1.0.0")),
+                (String::from("config_hash"), String::from("abc\"123")),
+            ],
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let mut logger = file_logger_with_config(temp_dir.path(), config.clone());
+            log_and_wait(&mut logger, "This is a test.");
+        }
+
+        let log_contents = std::fs::read_to_string(&path).unwrap();
+        assert!(log_contents.starts_with(expected));
+        assert_eq!(log_contents.matches("1.0.0").count(), 1);
+        assert_eq!(log_contents.matches("This is a test.").count(), 2);
+    }
+
+    #[rstest]
+    fn test_run_marker_delimits_runs_in_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("runs.json");
+        std::fs::write(&path, "{\"message\":\"Previous run.\"}\n").unwrap();
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(path.clone(), LogFileFormat::Json)],
+                run_marker: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+
+        let log_contents = std::fs::read_to_string(&path).unwrap();
+        let marker: Value = serde_json::from_str(log_contents.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(marker["run"]["pid"], Value::from(std::process::id()));
+        assert_eq!(
+            marker["run"]["instance_id"],
+            Value::from(logger.instance_id.to_string())
+        );
+        assert!(log_contents
+            .lines()
+            .nth(2)
+            .unwrap()
+            .contains("This is a test."));
+
+        let main_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        assert!(main_contents.starts_with(&format!("# run pid={} start=", std::process::id())));
+        assert!(main_contents
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(&format!("instance_id={}", logger.instance_id)));
+    }
+
+    #[rstest]
+    fn test_flush_barrier_follows_each_flush() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("test.json");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(json_path.clone(), LogFileFormat::Json)],
+                flush_barrier: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+        log_and_wait(&mut logger, "Second.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        assert_eq!(
+            log_contents.lines().collect::<Vec<_>>(),
+            vec![
+                "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: First.",
+                "# barrier",
+                "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: Second.",
+                "# barrier",
+            ]
+        );
+        let json_contents = std::fs::read_to_string(json_path).unwrap();
+        let barriers: Vec<&str> = json_contents.lines().skip(1).step_by(2).collect();
+        assert_eq!(barriers, vec!["{\"_barrier\":true}", "{\"_barrier\":true}"]);
+    }
+
+    #[rstest]
+    fn test_format_marker_written_to_new_files() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("test.json");
+        let existing_path = temp_dir.path().join("existing.log");
+        std::fs::write(&existing_path, "Previous run.\n").unwrap();
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![
+                    LogFileOutput::new(json_path.clone(), LogFileFormat::Json),
+                    LogFileOutput::new(existing_path.clone(), LogFileFormat::Plain),
+                ],
+                format_marker: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        assert_eq!(
+            log_contents.lines().next().unwrap(),
+            "# format version=1 template=\"{ts} {glyph}[{level}] {trader_id}.{component}: {message}\\n\""
+        );
+        let json_contents = std::fs::read_to_string(json_path).unwrap();
+        let marker: Value = serde_json::from_str(json_contents.lines().next().unwrap()).unwrap();
+        assert_eq!(marker["format"]["version"], Value::from(LOG_FORMAT_VERSION));
+        assert_eq!(marker["format"]["fields"]["timestamp"], "timestamp");
+        assert_eq!(marker["format"]["fields"]["message"], "message");
+        assert_eq!(
+            std::fs::read_to_string(existing_path).unwrap(),
+            "Previous run.\n1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: This is a test.\n"
+        );
+    }
+
+    #[rstest]
+    fn test_format_marker_and_file_header_written_once_to_new_files() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("test.json");
+        let config = LoggerConfig {
+            file_outputs: vec![LogFileOutput::new(json_path.clone(), LogFileFormat::Json)],
+            format_marker: true,
+            file_header: vec![(String::from("version"), String::from("1.0.0"))],
+            json_field_names: JsonFieldNames::new(HashMap::from([(
+                String::from("timestamp"),
+                String::from("ts"),
+            )]))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let mut logger = file_logger_with_config(temp_dir.path(), config.clone());
+            log_and_wait(&mut logger, "This is a test.");
+        }
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let log_lines: Vec<&str> = log_contents.lines().collect();
+        assert_eq!(log_lines.len(), 4);
+        assert!(log_lines[0].starts_with("# format version=1 template="));
+        assert_eq!(log_lines[1], "# version: 1.0.0");
+        assert!(log_lines[2].ends_with("This is a test."));
+        assert!(log_lines[3].ends_with("This is a test."));
+
+        let json_contents = std::fs::read_to_string(json_path).unwrap();
+        let json_lines: Vec<Value> = json_contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(json_lines.len(), 4);
+        assert_eq!(json_lines[0]["format"]["fields"]["timestamp"], "ts");
+        assert_eq!(json_lines[0]["format"]["fields"]["level"], "level");
+        assert_eq!(json_lines[1]["header"]["version"], "1.0.0");
+        assert_eq!(json_lines[2]["ts"], 1_650_000_000_000_000_u64);
+        assert_eq!(json_lines[3]["message"], "This is a test.");
+    }
+
+    #[rstest]
+    fn test_disk_space_monitor_without_floor_never_pauses() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut monitor =
+            DiskSpaceMonitor::new(temp_dir.path().to_path_buf(), None, Duration::ZERO);
+
+        assert_eq!(monitor.check(1_000), None);
+        assert!(!monitor.is_paused);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_disk_space_monitor_pauses_and_resumes() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut monitor = DiskSpaceMonitor::new(
+            temp_dir.path().to_path_buf(),
+            Some(u64::MAX),
+            Duration::from_secs(1),
+        );
+
+        let (level, message) = monitor.check(1_000_000_000).unwrap();
+        assert_eq!(level, LogLevel::Warning);
+        assert!(message.contains("pausing file logging"));
+        assert!(monitor.is_paused);
+
+        // Not checked again until the interval has elapsed
+        monitor.floor = Some(0);
+        assert_eq!(monitor.check(1_500_000_000), None);
+        assert!(monitor.is_paused);
+
+        let (level, message) = monitor.check(2_000_000_000).unwrap();
+        assert_eq!(level, LogLevel::Info);
+        assert!(message.contains("resuming file logging"));
+        assert!(!monitor.is_paused);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_file_writes_paused_below_disk_free_floor() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let writer = SlowWriter::new(Duration::ZERO);
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                disk_free_floor: Some(u64::MAX),
+                console_writer: Some(LogWriter::new(writer.clone())),
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let lines = writer.lines();
+        assert!(log_contents.is_empty());
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("This is a test."));
+        assert!(lines[1].contains("[WRN] TRADER-001.Logger: Free disk space"));
+    }
+
+    #[rstest]
+    fn test_log_counts_summary() {
+        let mut counts = LogCounts::default();
+        counts.levels.insert(LogLevel::Info, 3);
+        counts.levels.insert(LogLevel::Error, 1);
+        counts.components.insert(String::from("RiskEngine"), 3);
+        counts.components.insert(String::from("DataEngine"), 1);
+
+        assert_eq!(
+            counts.summary(1_500_000_000, 2, 1, 0),
+            vec![
+                "Run summary: duration=1.500s, dropped=2, sampled=1, rate_limited=0",
+                "Run summary: levels=[DBG=0, INF=3, WRN=0, ERR=1, CRT=0]",
+                "Run summary: components=[DataEngine=1, RiskEngine=3]",
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_shutdown_summary_written_to_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                shutdown_summary: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "This is a test.");
+        drop(logger);
+
+        let path = temp_dir.path().join("test.log");
+        let mut log_contents = String::new();
+        wait_until(
+            || {
+                log_contents = std::fs::read_to_string(&path).unwrap();
+                log_contents.contains("Run summary")
+            },
+            Duration::from_secs(2),
+        );
+
+        assert!(log_contents.contains("Logger: Run summary: duration="));
+        assert!(log_contents
+            .contains("Logger: Run summary: levels=[DBG=0, INF=1, WRN=0, ERR=0, CRT=0]\n"));
+        assert!(log_contents.contains("Logger: Run summary: components=[RiskEngine=1]\n"));
+    }
+
+    #[rstest]
+    fn test_shutdown_summary_bypasses_file_level_and_filters() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger = Logger::new(
+            TraderId::from("TRADER-001"),
+            String::from("user-01"),
+            UUID4::new(),
+            LogLevel::Warning,
+            Some(LogLevel::Warning),
+            Some(temp_dir.path().to_str().unwrap().to_string()),
+            Some("test".to_string()),
+            None,
+            Some(HashMap::from([(
+                String::from("Logger"),
+                Value::String(String::from("ERROR")),
+            )])),
+            false,
+            LoggerConfig {
+                shutdown_summary: true,
+                ..Default::default()
+            },
+        );
+        logger.warn(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+        logger.shutdown();
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let lines: Vec<&str> = log_contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with("[WRN] TRADER-001.RiskEngine: This is a test."));
+        assert!(lines[1].contains("[INF] TRADER-001.Logger: Run summary: duration="));
+        assert!(lines[2].ends_with("Run summary: levels=[DBG=0, INF=0, WRN=1, ERR=0, CRT=0]"));
+        assert!(lines[3].ends_with("Run summary: components=[RiskEngine=1]"));
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_subscribe_streams_events_as_json(mut logger: Logger) {
+        let rx = logger.subscribe(10);
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        );
+
+        let json = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(
+            json,
+            "{\"timestamp\":1650000000000000,\"level\":\"INFO\",\"color\":\"Normal\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}"
+        );
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_subscribe_with_zero_capacity_buffers_one_event(mut logger: Logger) {
+        let rx = logger.subscribe(0);
+
+        log_and_wait(&mut logger, "This is a test.");
+
+        assert!(rx.try_recv().unwrap().contains("This is a test."));
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_subscribe_drops_slow_subscriber(mut logger: Logger) {
+        let rx = logger.subscribe(1);
+
+        logger.info(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("First message."),
+        );
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("Second message."),
+            )
+            .unwrap();
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+
+        assert!(rx.recv().unwrap().contains("First message."));
+        assert!(rx.recv().is_err());
+        assert!(lock(&logger.state.subscribers).is_empty());
+    }
+
+    #[rstest]
+    #[case(
+        ConsoleColorScope::WholeLine,
+        "\x1b[1m1970-01-20T02:20:00.000000000Z\x1b[0m \x1b[1;33m[WRN] TRADER-001.RiskEngine: This is a test.\x1b[0m\n"
+    )]
+    #[case(
+        ConsoleColorScope::LevelOnly,
+        "\x1b[1m1970-01-20T02:20:00.000000000Z\x1b[0m \x1b[1;33m[WRN]\x1b[0m TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    #[case(
+        ConsoleColorScope::MessageOnly,
+        "\x1b[1m1970-01-20T02:20:00.000000000Z\x1b[0m [WRN] TRADER-001.RiskEngine: \x1b[1;33mThis is a test.\x1b[0m\n"
+    )]
+    #[case(
+        ConsoleColorScope::None,
+        "\x1b[1m1970-01-20T02:20:00.000000000Z\x1b[0m [WRN] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    fn test_format_log_line_console_color_scope(
+        #[case] scope: ConsoleColorScope,
+        #[case] expected: &str,
+    ) {
+        let event = log_event(
+            LogLevel::Warning,
+            LogColor::Yellow,
+            "RiskEngine",
+            "This is a test.",
+        );
+        let template = LogTemplate::new(&Logger::create_console_template(scope, "."));
+        let formatter = LineFormatter::new("TRADER-001", &LoggerConfig::default());
+
+        let line = formatter.format_console(&event, &template, "");
+
+        assert_eq!(line, expected);
+    }
+
+    #[rstest]
+    fn test_event_ids_reflect_send_order() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("test.json");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(json_path.clone(), LogFileFormat::Json)],
+                event_ids: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+        log_and_wait(&mut logger, "Second.");
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let json_contents = std::fs::read_to_string(json_path).unwrap();
+        let lines: Vec<&str> = log_contents.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "1970-01-20T02:20:00.000000000Z #1 [INF] TRADER-001.RiskEngine: First.",
+                "1970-01-20T02:20:00.000000000Z #2 [INF] TRADER-001.RiskEngine: Second.",
+            ]
+        );
+        let ids: Vec<Value> = json_contents
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["event_id"].clone())
+            .collect();
+        assert_eq!(ids, vec![Value::from(1_u64), Value::from(2_u64)]);
+    }
+
+    #[rstest]
+    fn test_event_ids_assigned_to_shutdown_summary() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                event_ids: true,
+                shutdown_summary: true,
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+        logger.shutdown();
+
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let ids: Vec<&str> = log_contents
+            .lines()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(ids, vec!["#1", "#2", "#3", "#4"]);
+    }
+
+    #[rstest]
+    fn test_format_log_line_console_json() {
+        let event = log_event(
+            LogLevel::Error,
+            LogColor::Red,
+            "RiskEngine",
+            "This is a test.",
+        );
+        let config = LoggerConfig {
+            console_json: true,
+            ..Default::default()
+        };
+        let template = LogTemplate::new(&Logger::create_console_template(
+            ConsoleColorScope::WholeLine,
+            ".",
+        ));
+        let formatter = LineFormatter::new("TRADER-001", &config);
+
+        let line = formatter.format_console(&event, &template, "");
+
+        assert_eq!(
+            line,
+            "{\"timestamp\":1650000000000000,\"level\":\"ERROR\",\"color\":\"Red\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}\n"
+        );
+    }
+
+    #[rstest]
+    fn test_format_log_line_with_timestamp_formatter() {
+        let event = log_event(
+            LogLevel::Info,
+            LogColor::Normal,
+            "RiskEngine",
+            "This is a test.",
+        );
+        let config = LoggerConfig {
+            timestamp_formatter: Some(TimestampFormatter::new(|ts| {
+                format!("T+{}s", ts / 1_000_000_000)
+            })),
+            ..Default::default()
+        };
+        let formatter = LineFormatter::new("TRADER-001", &config);
+        let template = LogTemplate::new("{ts} [{level}] {trader_id}.{component}: {message}\n");
+
+        assert_eq!(
+            formatter.format_file(&event, &template, false, ""),
+            "T+1650000s [INF] TRADER-001.RiskEngine: This is a test.\n"
+        );
+    }
+
+    #[rstest]
+    #[case(
+        LevelPadding::new(8),
+        LogLevel::Info,
+        "[INFO    ] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    #[case(
+        LevelPadding::new(8),
+        LogLevel::Warning,
+        "[WARNING ] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    #[case(
+        LevelPadding::new(8),
+        LogLevel::Critical,
+        "[CRITICAL] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    #[case(
+        LevelPadding { width: 8, fill: '.' },
+        LogLevel::Debug,
+        "[DEBUG...] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    #[case(
+        LevelPadding::new(2),
+        LogLevel::Error,
+        "[ERROR] TRADER-001.RiskEngine: This is a test.\n"
+    )]
+    fn test_format_log_line_level_padding(
+        #[case] padding: LevelPadding,
+        #[case] level: LogLevel,
+        #[case] expected: &str,
+    ) {
+        let event = log_event(level, LogColor::Normal, "RiskEngine", "This is a test.");
+        let config = LoggerConfig {
+            level_padding: Some(padding),
+            ..Default::default()
+        };
+        let formatter = LineFormatter::new("TRADER-001", &config);
+        let template = LogTemplate::new("{glyph}[{level}] {trader_id}.{component}: {message}\n");
+
+        assert_eq!(formatter.format_console(&event, &template, ""), expected);
+        assert_eq!(
+            formatter.format_file(&event, &template, false, ""),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(
+        LogTimestampFormat::Iso8601,
+        "1970-01-20T02:20:00.123456789Z",
+        1_650_000_123_456_789
+    )]
+    #[case(
+        LogTimestampFormat::Iso8601Digits(0),
+        "1970-01-20T02:20:00Z",
+        1_650_000_123_456_789
+    )]
+    #[case(
+        LogTimestampFormat::Iso8601Digits(3),
+        "1970-01-20T02:20:00.123Z",
+        1_650_000_123_456_789
+    )]
+    #[case(
+        LogTimestampFormat::Iso8601Digits(6),
+        "1970-01-20T02:20:00.123456Z",
+        1_650_000_123_456_789
+    )]
+    #[case(
+        LogTimestampFormat::Iso8601Digits(12),
+        "1970-01-20T02:20:00.123456789Z",
+        1_650_000_123_456_789
+    )]
+    #[case(
+        LogTimestampFormat::UnixNanos,
+        "1650000123456789",
+        1_650_000_123_456_789
+    )]
+    #[case(LogTimestampFormat::UnixMicros, "1650000123456", 1_650_000_123_456)]
+    #[case(LogTimestampFormat::UnixMillis, "1650000123", 1_650_000_123)]
+    fn test_format_log_line_with_timestamp_format(
+        #[case] timestamp_format: LogTimestampFormat,
+        #[case] expected_ts: &str,
+        #[case] expected_json_ts: u64,
+    ) {
+        let event = LogEvent {
+            timestamp: 1_650_000_123_456_789,
+            ..log_event(
+                LogLevel::Info,
+                LogColor::Normal,
+                "RiskEngine",
+                "This is a test.",
+            )
+        };
+        let config = LoggerConfig {
+            timestamp_format,
+            ..Default::default()
+        };
+        let formatter = LineFormatter::new("TRADER-001", &config);
+
+        let line = formatter.format_file(&event, &LogTemplate::new("{ts} {message}"), false, "");
+        let json: Value = serde_json::from_str(&formatter.format_json(&event)).unwrap();
+
+        assert_eq!(line, format!("{expected_ts} This is a test."));
+        assert_eq!(json["timestamp"], expected_json_ts);
+    }
+
+    #[rstest]
+    fn test_pause_holds_events_until_resumed() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let log_path = temp_dir.path().join("test.log");
+        let mut logger = file_logger(temp_dir.path(), LogFileConflict::Error);
+        log_and_wait(&mut logger, "Before pause.");
+
+        logger.pause();
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("During pause."),
+            )
+            .unwrap();
+
+        assert!(!logger.wait_flushed(token, Duration::from_millis(100)));
+        assert_eq!(
+            std::fs::read_to_string(&log_path).unwrap().lines().count(),
+            1
+        );
+
+        logger.resume();
+
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+        let log_contents = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(log_contents.lines().count(), 2);
+        assert!(log_contents.ends_with("During pause.\n"));
+    }
+
+    #[rstest]
+    fn test_info_default_uses_default_component() {
+        let mut logger = logger_with_config(LoggerConfig {
+            capture: true,
+            default_component: Arc::from("MyTool"),
+            ..Default::default()
+        });
+
+        logger.info_default(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("This is a test."),
+        );
+
+        let captured = logger.take_captured();
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].component, "MyTool");
+        assert_eq!(captured[0].level, LogLevel::Info);
+    }
+
+    #[rstest]
+    fn test_new_logger_with_empty_default_component_records_error() {
+        let mut logger = logger_with_config(LoggerConfig {
+            capture: true,
+            default_component: Arc::from(""),
+            ..Default::default()
+        });
+
+        logger.info_default(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("This is a test."),
+        );
+
+        let captured = logger.take_captured();
+        let last_error = lock(&logger.state.last_error).clone().unwrap();
+        assert_eq!(captured[0].component, "Logger");
+        assert!(last_error.contains("`default_component`"));
+    }
+
+    #[rstest]
+    fn test_format_log_line_with_identifiers() {
+        let event = LogEvent {
+            identifiers: Some(Box::new(
+                LogIdentifiers::new()
+                    .instrument_id(InstrumentId::from("ETHUSDT-PERP.BINANCE"))
+                    .client_order_id(ClientOrderId::from("O-20230901-001")),
+            )),
+            ..log_event(
+                LogLevel::Info,
+                LogColor::Normal,
+                "ExecEngine",
+                "Order filled.",
+            )
+        };
+        let formatter = LineFormatter::new("TRADER-001", &LoggerConfig::default());
+
+        let line = formatter.format_file(
+            &event,
+            &LogTemplate::new("{component}: {message}"),
+            false,
+            "",
+        );
+        let json: Value = serde_json::from_str(&formatter.format_json(&event)).unwrap();
+
+        assert_eq!(
+            line,
+            "ExecEngine: Order filled. instrument_id=ETHUSDT-PERP.BINANCE client_order_id=O-20230901-001"
+        );
+        assert_eq!(json["message"], "Order filled.");
+        assert_eq!(json["instrument_id"], "ETHUSDT-PERP.BINANCE");
+        assert_eq!(json["client_order_id"], "O-20230901-001");
+        assert_eq!(json["position_id"], Value::Null);
+    }
+
+    #[rstest]
+    fn test_send_with_identifiers(mut logger: Logger) {
+        logger.set_capturing(true);
+
+        logger
+            .send_with_identifiers(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("ExecEngine"),
+                String::from("Position opened."),
+                LogIdentifiers::new().position_id(PositionId::from("P-001")),
+            )
+            .unwrap();
+
+        let captured = logger.take_captured();
+        let json: Value = serde_json::to_value(&captured[0]).unwrap();
+        let identifiers = captured[0].identifiers.as_ref().unwrap();
+        assert_eq!(identifiers.position_id.as_deref(), Some("P-001"));
+        assert_eq!(json["position_id"], "P-001");
+        assert!(json.get("identifiers").is_none());
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(LogIdentifiers::new().trade_id(TradeId::from("T-001"))))]
+    fn test_log_event_identifiers_round_trip(#[case] identifiers: Option<LogIdentifiers>) {
+        let event = LogEvent {
+            identifiers: identifiers.clone().map(Box::new),
+            ..log_event(
+                LogLevel::Info,
+                LogColor::Normal,
+                "ExecEngine",
+                "This is a test.",
+            )
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        let deserialized: LogEvent = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.identifiers.map(|ids| *ids), identifiers);
+    }
+
+    #[rstest]
+    fn test_level_remap_downgrades_matching_events() {
+        let mut logger = logger_with_config(LoggerConfig {
+            capture: true,
+            level_remaps: vec![LogLevelRemap::new(
+                String::from("ThirdPartyAdapter"),
+                String::from("connection reset"),
+                Some(LogLevel::Error),
+                LogLevel::Warning,
+            )],
+            ..Default::default()
+        });
+
+        for (component, message) in [
+            ("ThirdPartyAdapter", "Benign connection reset by peer."),
+            ("ThirdPartyAdapter", "Authentication failed."),
+            ("RiskEngine", "Benign connection reset by peer."),
+        ] {
+            logger.error(
+                1_650_000_000_000_000,
+                LogColor::Red,
+                String::from(component),
+                String::from(message),
+            );
+        }
+
+        let levels: Vec<LogLevel> = logger.take_captured().iter().map(|e| e.level).collect();
+        assert_eq!(
+            levels,
+            vec![LogLevel::Warning, LogLevel::Error, LogLevel::Error]
+        );
+    }
+
+    #[rstest]
+    fn test_try_log_drops_when_channel_full() {
+        let mut logger = logger_with_config(LoggerConfig {
+            channel_capacity: Some(1),
+            ..Default::default()
+        });
+
+        // Keep sending until the logging thread falls behind and the channel is full
+        while logger.try_log(
+            1_650_000_000_000_000,
+            LogLevel::Info,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        ) {}
+
+        assert_eq!(logger.dropped_count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_emergency_writes_line() {
+        use std::os::unix::io::FromRawFd;
+
+        let mut fds = [0; 2];
+        // Safety: `fds` has room for the read and write file descriptors
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        // Safety: the pipe file descriptors are open and owned by these files from here on
+        let (mut reader, writer) =
+            unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+        let is_written = Logger::emergency_to_fd(
+            fds[1],
+            LogLevel::Critical,
+            "This is a test emergency message.",
+        );
+        drop(writer);
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+
+        assert!(is_written);
+        assert_eq!(output, "[CRT] This is a test emergency message.\n");
+    }
+
+    #[rstest]
+    fn test_slow_sink_try_log_drops_when_channel_full() {
+        let (mut logger, writer) = slow_logger(
+            Duration::from_millis(20),
+            LoggerConfig {
+                channel_capacity: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let sent = (0..10)
+            .filter(|i| {
+                logger.try_log(
+                    1_650_000_000_000_000,
+                    LogLevel::Info,
+                    LogColor::Normal,
+                    String::from("RiskEngine"),
+                    format!("Event {i}."),
+                )
+            })
+            .count() as u64;
+
+        assert!(sent < 10);
+        assert_eq!(logger.dropped_count(), 10 - sent);
+        wait_until(
+            || writer.lines().len() as u64 == sent,
+            Duration::from_secs(2),
+        );
+    }
+
+    #[rstest]
+    fn test_slow_sink_send_blocks_without_dropping() {
+        let (mut logger, writer) = slow_logger(
+            Duration::from_millis(20),
+            LoggerConfig {
+                channel_capacity: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let mut token = 0;
+        for i in 0..5 {
+            token = logger
+                .send(
+                    1_650_000_000_000_000,
+                    LogLevel::Info,
+                    LogColor::Normal,
+                    String::from("RiskEngine"),
+                    format!("Event {i}."),
+                )
+                .unwrap();
+        }
+
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+        assert_eq!(writer.lines().len(), 5);
+        assert!(writer.lines()[4].ends_with("Event 4.\x1b[0m"));
+        assert_eq!(logger.dropped_count(), 0);
+    }
+
+    #[rstest]
+    fn test_slow_sink_pause_overflow_drops() {
+        let (mut logger, writer) = slow_logger(
+            Duration::from_millis(1),
+            LoggerConfig {
+                pause_capacity: 2,
+                ..Default::default()
+            },
+        );
+
+        logger.pause();
+        let mut token = 0;
+        for i in 0..5 {
+            token = logger
+                .send(
+                    1_650_000_000_000_000,
+                    LogLevel::Info,
+                    LogColor::Normal,
+                    String::from("RiskEngine"),
+                    format!("Event {i}."),
+                )
+                .unwrap();
+        }
+        logger.resume();
+
+        // Events dropped while paused are marked flushed, so the last token completes once the
+        // held events are written
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+        assert_eq!(writer.lines().len(), 2);
+        assert_eq!(logger.dropped_count(), 3);
+    }
+
+    #[rstest]
+    fn test_send_after_shutdown_returns_error() {
+        let (mut logger, writer) = slow_logger(Duration::ZERO, LoggerConfig::default());
+        logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("Before shutdown."),
+            )
+            .unwrap();
+
+        logger.shutdown();
+        let result = logger.send(
+            1_650_000_000_000_000,
+            LogLevel::Info,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("After shutdown."),
+        );
+
+        assert_eq!(result, Err(LoggerShuttingDown));
+        assert!(!logger.try_log(
+            1_650_000_000_000_000,
+            LogLevel::Info,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("After shutdown."),
+        ));
+        assert_eq!(logger.rejected_count(), 2);
+        assert_eq!(logger.dropped_count(), 0);
+        assert_eq!(writer.lines().len(), 1);
+    }
+
+    #[rstest]
+    fn test_shutdown_waits_for_accepted_sends() {
+        let (mut logger, writer) = slow_logger(
+            Duration::ZERO,
+            LoggerConfig {
+                timer_level: LogLevel::Info,
+                ..Default::default()
+            },
+        );
+        let timers: Vec<LogTimer> = (0..50)
+            .map(|i| logger.timer(String::from("RiskEngine"), format!("Timer {i}")))
+            .collect();
+
+        // Each event is either written or rejected, never lost between the two
+        let handles: Vec<_> = timers
+            .into_iter()
+            .map(|timer| thread::spawn(move || drop(timer)))
+            .collect();
+        logger.shutdown();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(writer.lines().len() as u64 + logger.rejected_count(), 50);
+        assert_eq!(logger.dropped_count(), 0);
+    }
+
+    #[rstest]
+    fn test_log_batch_writes_events_in_order(mut logger: Logger) {
+        logger.set_capturing(true);
+        let events = (1..=3)
+            .map(|i| {
+                LogEvent::new(
+                    1_650_000_000_000_000 + i,
+                    LogLevel::Info,
+                    LogColor::Normal,
+                    String::from("Replay"),
+                    format!("Event {i}."),
+                )
+            })
+            .collect();
+
+        let token = logger.log_batch(events).unwrap();
+
+        assert_eq!(token, 3);
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+        let messages: Vec<String> = logger
+            .take_captured()
+            .into_iter()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(messages, vec!["Event 1.", "Event 2.", "Event 3."]);
+    }
+
+    #[rstest]
+    fn test_log_batch_empty_returns_last_token(mut logger: Logger) {
+        let token = logger
+            .send(
+                1_650_000_000_000_000,
+                LogLevel::Info,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from("This is a test."),
+            )
+            .unwrap();
+
+        assert_eq!(logger.log_batch(Vec::new()), Ok(token));
+    }
+
+    #[rstest]
+    fn test_try_log_enqueues_event(mut logger: Logger) {
+        logger.set_capturing(true);
+
+        assert!(logger.try_log(
+            1_650_000_000_000_000,
+            LogLevel::Info,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            String::from("This is a test."),
+        ));
+        assert_eq!(logger.take_captured().len(), 1);
+        assert_eq!(logger.dropped_count(), 0);
+    }
+
+    #[rstest]
+    #[case(MessageEscaping::None, "a\tb\n\"c\"", "a\tb\n\"c\"")]
+    #[case(MessageEscaping::ControlChars, "a\tb\n\"c\"", "a\\x09b\\x0a\"c\"")]
+    #[case(MessageEscaping::Full, "a\tb\n\"c\"", "a\\x09b\\x0a\\\"c\\\"")]
+    #[case(MessageEscaping::Full, "it's C:\\", "it\\'s C:\\\\")]
+    #[case(MessageEscaping::Full, "µs", "µs")]
+    fn test_message_escaping(
+        #[case] escaping: MessageEscaping,
+        #[case] message: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(escaping.escape(message), expected);
+    }
+
+    #[rstest]
+    #[case(ConsoleStreamPolicy::Split, LogLevel::Info, false)]
+    #[case(ConsoleStreamPolicy::Split, LogLevel::Error, true)]
+    #[case(ConsoleStreamPolicy::Split, LogLevel::Critical, true)]
+    #[case(ConsoleStreamPolicy::SingleStdout, LogLevel::Info, false)]
+    #[case(ConsoleStreamPolicy::SingleStdout, LogLevel::Error, false)]
+    #[case(ConsoleStreamPolicy::SingleStdout, LogLevel::Critical, false)]
+    fn test_console_stream_policy_is_stderr(
+        #[case] policy: ConsoleStreamPolicy,
+        #[case] level: LogLevel,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(policy.is_stderr(level), expected);
+    }
+
+    #[rstest]
+    #[case(ConsoleStreamPolicy::Split, LogLevel::Info, false)]
+    #[case(ConsoleStreamPolicy::Split, LogLevel::Error, true)]
+    #[case(ConsoleStreamPolicy::SingleStdout, LogLevel::Error, false)]
+    fn test_console_colors_only_disabled_for_failed_stream(
+        #[case] policy: ConsoleStreamPolicy,
+        #[case] level: LogLevel,
+        #[case] expected: bool,
+    ) {
+        let colors = ConsoleColors {
+            stdout: false,
+            stderr: true,
+        };
+
+        assert_eq!(colors.is_colored(level, policy), expected);
+    }
+
+    #[cfg(windows)]
+    #[rstest]
+    fn test_enable_virtual_terminal_fails_for_non_console_handle() {
+        let file = tempfile::tempfile().unwrap();
+
+        assert!(!Logger::enable_virtual_terminal(
+            std::os::windows::io::AsRawHandle::as_raw_handle(&file)
+        ));
+    }
+
+    #[rstest]
+    fn test_log_template_parses_segments() {
+        let template = LogTemplate::new("{ts} [{level}] {unknown} {message}\n{");
+
+        assert_eq!(
+            template.segments,
+            vec![
+                TemplateSegment::Timestamp,
+                TemplateSegment::Literal(String::from(" [")),
+                TemplateSegment::Level,
+                TemplateSegment::Literal(String::from("] {unknown} ")),
+                TemplateSegment::Message,
+                TemplateSegment::Literal(String::from("\n{")),
+            ]
+        );
+        assert_eq!(template.literal_len, 16);
+    }
+
+    #[rstest]
+    fn test_log_template_with_prefix_is_literal() {
+        let template = LogTemplate::new("{level}: {message}").with_prefix("[{HEDGE}] ");
+
+        assert_eq!(
+            template.segments,
+            vec![
+                TemplateSegment::Literal(String::from("[{HEDGE}] ")),
+                TemplateSegment::Level,
+                TemplateSegment::Literal(String::from(": ")),
+                TemplateSegment::Message,
+            ]
+        );
+        assert_eq!(template.literal_len, 12);
+    }
+
+    #[rstest]
+    fn test_lazy_message_only_built_when_logged(mut logger: Logger) {
+        let mut built = Vec::new();
+
+        logger.debug_lazy(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            || {
+                built.push(LogLevel::Debug);
+                String::from("Debug message.")
+            },
+        );
+        logger.info_lazy(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            || {
+                built.push(LogLevel::Info);
+                String::from("Info message.")
+            },
+        );
+        logger.is_bypassed = true;
+        logger.error_lazy(
+            1_650_000_000_000_000,
+            LogColor::Normal,
+            String::from("RiskEngine"),
+            || {
+                built.push(LogLevel::Error);
+                String::from("Error message.")
+            },
+        );
+
+        assert_eq!(built, vec![LogLevel::Info]);
+    }
+
+    #[rstest]
+    #[case(LogLevel::Debug, false)]
+    #[case(LogLevel::Info, true)]
+    #[case(LogLevel::Error, true)]
+    fn test_would_log(logger: Logger, #[case] level: LogLevel, #[case] expected: bool) {
+        assert_eq!(logger.would_log(level), expected);
+    }
+
+    #[rstest]
+    fn test_run_id_in_file_name_and_lines() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let json_path = temp_dir.path().join("test.json");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput::new(json_path.clone(), LogFileFormat::Json)],
+                run_id: Some(String::from("sweep-7")),
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+
+        let log_path = temp_dir.path().join("test_sweep-7.log");
+        assert_eq!(logger.current_file_path(), Some(log_path.clone()));
+        assert_eq!(
+            std::fs::read_to_string(log_path).unwrap(),
+            "1970-01-20T02:20:00.000000000Z run_id=sweep-7 [INF] TRADER-001.RiskEngine: First.\n"
+        );
+        let json: Value =
+            serde_json::from_str(std::fs::read_to_string(json_path).unwrap().trim_end()).unwrap();
+        assert_eq!(json["run_id"], "sweep-7");
+    }
+
+    #[rstest]
+    fn test_run_id_path_separators_replaced() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                run_id: Some(String::from("../sweep/7\\a")),
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+
+        let log_path = temp_dir.path().join("test___sweep_7_a.log");
+        assert_eq!(logger.current_file_path(), Some(log_path.clone()));
+        assert!(std::fs::read_to_string(log_path)
+            .unwrap()
+            .contains("run_id=__sweep_7_a [INF]"));
+    }
+
+    #[rstest]
+    fn test_invalid_run_id_recorded_and_ignored() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                run_id: Some(String::from(" ")),
+                ..Default::default()
+            },
+        );
+        log_and_wait(&mut logger, "First.");
+
+        let last_error = lock(&logger.state.last_error).clone().unwrap();
+        assert_eq!(
+            logger.current_file_path(),
+            Some(temp_dir.path().join("test.log"))
+        );
+        assert!(last_error.contains("`run_id`"));
     }
 
     #[rstest]
-    fn test_new_logger(logger: Logger) {
-        assert_eq!(logger.trader_id, TraderId::from("TRADER-001"));
-        assert_eq!(logger.level_stdout, LogLevel::Info);
-        assert_eq!(logger.level_file, None);
-        assert!(!logger.is_bypassed);
+    fn test_run_id_in_default_file_name() {
+        let path = Logger::create_log_file_path(
+            &None,
+            &None,
+            "TRADER-001",
+            "5d3b5e5e-0000-4000-8000-000000000001",
+            Some("sweep-7"),
+            false,
+        );
+        let file_name = path.to_str().unwrap();
+
+        assert!(file_name.starts_with("TRADER-001_"));
+        assert!(file_name.ends_with("_sweep-7_5d3b5e5e-0000-4000-8000-000000000001.log"));
+        assert!(Logger::is_rotated_log_file(file_name, "TRADER-001"));
     }
 
     #[rstest]
-    fn test_logger_debug(mut logger: Logger) {
-        logger.debug(
-            1_650_000_000_000_000,
-            LogColor::Normal,
-            String::from("RiskEngine"),
-            String::from("This is a test debug message."),
-        );
+    fn test_query_buffer_by_time_range_and_level() {
+        let mut logger = logger_with_config(LoggerConfig {
+            buffer_capacity: 10,
+            ..Default::default()
+        });
+        let mut token = 0;
+        for (timestamp, level) in [
+            (1, LogLevel::Info),
+            (2, LogLevel::Debug),
+            (3, LogLevel::Warning),
+            (4, LogLevel::Info),
+            (5, LogLevel::Info),
+        ] {
+            token = logger
+                .send(
+                    timestamp,
+                    level,
+                    LogColor::Normal,
+                    String::from("RiskEngine"),
+                    format!("Message {timestamp}."),
+                )
+                .unwrap();
+        }
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
+
+        let messages: Vec<String> = logger
+            .query_buffer(2, 4, LogLevel::Info)
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+
+        assert_eq!(messages, vec!["Message 3.", "Message 4."]);
+        assert!(logger.query_buffer(6, 10, LogLevel::Debug).is_empty());
     }
 
     #[rstest]
-    fn test_logger_info(mut logger: Logger) {
+    #[case(EmptyMessagePolicy::Keep, vec!["[INF] TRADER-001.RiskEngine: ", "[INF] TRADER-001.RiskEngine: Last."])]
+    #[case(EmptyMessagePolicy::Drop, vec!["[INF] TRADER-001.RiskEngine: Last."])]
+    #[case(EmptyMessagePolicy::Placeholder, vec!["[INF] TRADER-001.RiskEngine: <empty>", "[INF] TRADER-001.RiskEngine: Last."])]
+    fn test_empty_message_policy(#[case] policy: EmptyMessagePolicy, #[case] expected: Vec<&str>) {
+        let (mut logger, writer) = slow_logger(
+            Duration::ZERO,
+            LoggerConfig {
+                empty_message_policy: policy,
+                ..Default::default()
+            },
+        );
         logger.info(
             1_650_000_000_000_000,
             LogColor::Normal,
             String::from("RiskEngine"),
-            String::from("This is a test info message."),
+            String::new(),
         );
+        log_and_wait(&mut logger, "Last.");
+
+        let lines: Vec<String> = writer
+            .lines()
+            .iter()
+            .map(|line| {
+                line[line.find("[INF]").unwrap()..]
+                    .trim_end_matches("\x1b[0m")
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(lines, expected);
     }
 
     #[rstest]
-    fn test_logger_error(mut logger: Logger) {
-        logger.error(
+    fn test_take_and_reset_counts(mut logger: Logger) {
+        logger.debug(
             1_650_000_000_000_000,
             LogColor::Normal,
             String::from("RiskEngine"),
-            String::from("This is a test error message."),
+            String::from("Debug."),
         );
-    }
-
-    #[rstest]
-    fn test_logger_critical(mut logger: Logger) {
-        logger.critical(
+        logger.warn(
             1_650_000_000_000_000,
-            LogColor::Normal,
+            LogColor::Yellow,
             String::from("RiskEngine"),
-            String::from("This is a test critical message."),
+            String::from("Warning."),
+        );
+        log_and_wait(&mut logger, "First.");
+        log_and_wait(&mut logger, "Second.");
+
+        assert_eq!(
+            logger.take_and_reset_counts(),
+            LogCountsSnapshot {
+                debug: 1,
+                info: 2,
+                warning: 1,
+                ..Default::default()
+            }
         );
+        assert_eq!(logger.take_and_reset_counts(), LogCountsSnapshot::default());
+
+        log_and_wait(&mut logger, "Third.");
+        assert_eq!(logger.take_and_reset_counts().info, 1);
     }
 
     #[rstest]
-    fn test_logging_to_file() {
+    fn test_circular_log_file_wraps_and_resumes() {
         let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("circular.log");
+        let config = || LoggerConfig {
+            circular_files: vec![CircularLogFile::new(
+                path.clone(),
+                LogFileFormat::Plain,
+                200,
+                LogLevel::Info,
+            )],
+            ..Default::default()
+        };
+        let line = |n: usize| {
+            format!("1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: Event {n}.")
+        };
 
-        let mut logger = Logger::new(
-            TraderId::from("TRADER-001"),
-            String::from("user-01"),
-            UUID4::new(),
-            LogLevel::Info,
-            Some(LogLevel::Debug),
-            Some(temp_dir.path().to_str().unwrap().to_string()),
-            None,
-            None,
-            None,
-            false,
+        // Each line is 69 bytes, so only two whole lines fit the capacity
+        let (mut logger, _) = slow_logger(Duration::ZERO, config());
+        log_and_wait(&mut logger, "Event 1.");
+        log_and_wait(&mut logger, "Event 2.");
+        logger.checkpoint().unwrap();
+        assert_eq!(
+            read_circular_log_file(&path).unwrap(),
+            vec![line(1), line(2)]
         );
 
-        logger.info(
-            1_650_000_000_000_000,
-            LogColor::Normal,
-            String::from("RiskEngine"),
-            String::from("This is a test."),
+        for n in 3..=5 {
+            log_and_wait(&mut logger, &format!("Event {n}."));
+        }
+        logger.checkpoint().unwrap();
+        assert_eq!(
+            read_circular_log_file(&path).unwrap(),
+            vec![line(4), line(5)]
         );
-
-        let mut log_contents = String::new();
-
-        wait_until(
-            || {
-                let log_file_exists = std::fs::read_dir(&temp_dir)
-                    .expect("Failed to read directory")
-                    .filter_map(Result::ok)
-                    .filter(|entry| entry.path().is_file())
-                    .next()
-                    .is_some();
-
-                log_file_exists
-            },
-            Duration::from_secs(2),
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            CIRCULAR_LOG_HEADER_LEN + 200
         );
+        logger.shutdown();
 
-        wait_until(
-            || {
-                let log_file_path = std::fs::read_dir(&temp_dir)
-                    .expect("Failed to read directory")
-                    .filter_map(Result::ok)
-                    .filter(|entry| entry.path().is_file())
-                    .next()
-                    .expect("No files found in directory")
-                    .path();
-                log_contents =
-                    std::fs::read_to_string(&log_file_path).expect("Error while reading log file");
-                !log_contents.is_empty()
-            },
-            Duration::from_secs(2),
+        let (mut logger, _) = slow_logger(Duration::ZERO, config());
+        log_and_wait(&mut logger, "Event 6.");
+        logger.checkpoint().unwrap();
+        assert_eq!(
+            read_circular_log_file(&path).unwrap(),
+            vec![line(5), line(6)]
         );
+    }
+
+    #[rstest]
+    fn test_circular_log_file_wrap_on_line_boundary_keeps_oldest_line() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("circular.log");
+        let config = LoggerConfig {
+            circular_files: vec![CircularLogFile::new(
+                path.clone(),
+                LogFileFormat::Plain,
+                138,
+                LogLevel::Info,
+            )],
+            ..Default::default()
+        };
+        let line = |n: usize| {
+            format!("1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: Event {n}.")
+        };
 
+        // Each line is 69 bytes, so the writes wrap exactly at the end of every second line
+        let (mut logger, _) = slow_logger(Duration::ZERO, config);
+        for n in 1..=3 {
+            log_and_wait(&mut logger, &format!("Event {n}."));
+        }
+        logger.checkpoint().unwrap();
         assert_eq!(
-            log_contents,
-            "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: This is a test.\n"
+            read_circular_log_file(&path).unwrap(),
+            vec![line(2), line(3)]
         );
+        let header = std::fs::read(&path).unwrap();
+        assert_eq!(header[24], 1);
+        assert_eq!(header[25], 1);
     }
 
     #[rstest]
-    fn test_log_component_level_filtering() {
+    fn test_circular_log_file_in_use_by_another_logger() {
         let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("circular.log");
+        let config = || LoggerConfig {
+            circular_files: vec![CircularLogFile::new(
+                path.clone(),
+                LogFileFormat::Plain,
+                200,
+                LogLevel::Info,
+            )],
+            ..Default::default()
+        };
 
-        let mut logger = Logger::new(
-            TraderId::from("TRADER-001"),
-            String::from("user-01"),
-            UUID4::new(),
-            LogLevel::Info,
-            Some(LogLevel::Debug),
-            Some(temp_dir.path().to_str().unwrap().to_string()),
-            None,
-            None,
-            Some(HashMap::from_iter(std::iter::once((
-                String::from("RiskEngine"),
-                Value::from("ERROR"), // <-- This should be filtered
-            )))),
-            false,
-        );
+        let (mut logger1, _) = slow_logger(Duration::ZERO, config());
+        log_and_wait(&mut logger1, "First logger.");
+        let (mut logger2, _) = slow_logger(Duration::ZERO, config());
+        log_and_wait(&mut logger2, "Second logger.");
+        logger1.checkpoint().unwrap();
 
-        logger.info(
-            1_650_000_000_000_000,
-            LogColor::Normal,
-            String::from("RiskEngine"),
-            String::from("This is a test."),
+        let lines = read_circular_log_file(&path).unwrap();
+        let last_error = lock(&logger2.state.last_error).clone().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("First logger."));
+        assert!(last_error.contains("already in use by another logger"));
+    }
+
+    fn circular_log_bytes(capacity: u64, offset: u64, data_len: usize) -> Vec<u8> {
+        let mut bytes = b"NTCIRLOG".to_vec();
+        bytes.extend_from_slice(&capacity.to_le_bytes());
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.resize(CIRCULAR_LOG_HEADER_LEN as usize + data_len, 0);
+        bytes
+    }
+
+    #[rstest]
+    #[case(b"Not a circular log file.\n".to_vec())]
+    #[case(circular_log_bytes(8, 9, 8))] // Offset beyond capacity
+    #[case(circular_log_bytes(u64::MAX, 0, 8))] // Capacity overflows file length
+    #[case(circular_log_bytes(16, 0, 8))] // Data shorter than capacity
+    fn test_read_circular_log_file_rejects_invalid_header(#[case] bytes: Vec<u8>) {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("plain.log");
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = read_circular_log_file(&path);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[rstest]
+    #[case(false, vec!["A"])] // The oldest line was partly overwritten
+    #[case(true, vec!["B", "A"])]
+    fn test_read_circular_log_file_skips_only_partial_line(
+        #[case] is_line_boundary: bool,
+        #[case] expected: Vec<&str>,
+    ) {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let path = temp_dir.path().join("circular.log");
+        let mut bytes = circular_log_bytes(4, 2, 0);
+        bytes[24] = 1;
+        bytes[25] = u8::from(is_line_boundary);
+        bytes.extend_from_slice(b"A\nB\n");
+        std::fs::write(&path, bytes).unwrap();
+
+        let lines = read_circular_log_file(&path).unwrap();
+
+        assert_eq!(lines, expected);
+    }
+
+    #[rstest]
+    fn test_escape_non_ascii() {
+        assert_eq!(
+            escape_non_ascii("Café ☕ 🚀", true, false),
+            "Caf\\u{00E9} \\u{2615} \\u{1F680}"
+        );
+        assert_eq!(
+            escape_non_ascii("Café ☕ 🚀", true, true),
+            "Caf\\u00e9 \\u2615 \\ud83d\\ude80"
         );
+        assert_eq!(escape_non_ascii("Café", false, false), "Café");
+        assert!(matches!(
+            escape_non_ascii("Plain.", true, true),
+            Cow::Borrowed(_)
+        ));
+    }
 
-        wait_until(
-            || {
-                if let Some(log_file) = std::fs::read_dir(&temp_dir)
-                    .expect("Failed to read directory")
-                    .filter_map(Result::ok)
-                    .filter(|entry| entry.path().is_file())
-                    .next()
-                {
-                    let log_file_path = log_file.path();
-                    let log_contents = std::fs::read_to_string(&log_file_path)
-                        .expect("Error while reading log file");
-                    !log_contents.contains("RiskEngine")
-                } else {
-                    false
-                }
+    #[rstest]
+    fn test_ascii_only_applies_per_sink() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let ascii_path = temp_dir.path().join("ascii.log");
+        let writer = SlowWriter::new(Duration::ZERO);
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput {
+                    ascii_only: true,
+                    ..LogFileOutput::new(ascii_path.clone(), LogFileFormat::Plain)
+                }],
+                console_writer: Some(LogWriter::new(writer.clone())),
+                console_ascii_only: true,
+                ..Default::default()
             },
-            Duration::from_secs(3),
         );
+        log_and_wait(&mut logger, "Café ☕ 🚀");
 
-        assert!(
-            std::fs::read_dir(&temp_dir)
-                .expect("Failed to read directory")
-                .filter_map(Result::ok)
-                .filter(|entry| entry.path().is_file())
-                .next()
-                .is_some(),
-            "Log file exists"
-        );
+        let main_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        let ascii_contents = std::fs::read_to_string(ascii_path).unwrap();
+        assert!(main_contents.ends_with("RiskEngine: Café ☕ 🚀\n"));
+        assert!(ascii_contents.is_ascii());
+        assert!(ascii_contents.ends_with("RiskEngine: Caf\\u{00E9} \\u{2615} \\u{1F680}\n"));
+        assert!(writer.lines()[0].is_ascii());
+        assert!(writer.lines()[0].contains("Caf\\u{00E9} \\u{2615} \\u{1F680}"));
     }
 
     #[rstest]
-    fn test_logging_to_file_in_json_format() {
+    fn test_ascii_only_json_sink_decodes_to_original_message() {
         let temp_dir = tempdir().expect("Failed to create temporary directory");
-
-        let mut logger = Logger::new(
-            TraderId::from("TRADER-001"),
-            String::from("user-01"),
-            UUID4::new(),
-            LogLevel::Info,
-            Some(LogLevel::Debug),
-            Some(temp_dir.path().to_str().unwrap().to_string()),
-            None,
-            Some("json".to_string()),
-            None,
-            false,
+        let json_path = temp_dir.path().join("ascii.json");
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                file_outputs: vec![LogFileOutput {
+                    ascii_only: true,
+                    ..LogFileOutput::new(json_path.clone(), LogFileFormat::Json)
+                }],
+                ..Default::default()
+            },
         );
+        log_and_wait(&mut logger, "Café ☕ 🚀");
 
-        logger.info(
-            1_650_000_000_000_000,
-            LogColor::Normal,
-            String::from("RiskEngine"),
-            String::from("This is a test."),
+        let json_contents = std::fs::read_to_string(json_path).unwrap();
+        let json: Value = serde_json::from_str(json_contents.trim_end()).unwrap();
+        assert!(json_contents.is_ascii());
+        assert!(json_contents.contains("Caf\\u00e9 \\u2615 \\ud83d\\ude80"));
+        assert_eq!(json["message"], "Café ☕ 🚀");
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_subscriber_ascii_only_escapes_published_json() {
+        let mut logger = logger_with_config(LoggerConfig {
+            subscriber_ascii_only: true,
+            ..Default::default()
+        });
+        let rx = logger.subscribe(10);
+        log_and_wait(&mut logger, "Café ☕ 🚀");
+
+        let published = rx.try_recv().unwrap();
+        let json: Value = serde_json::from_str(&published).unwrap();
+        assert!(published.is_ascii());
+        assert!(published.contains("Caf\\u00e9 \\u2615 \\ud83d\\ude80"));
+        assert_eq!(json["message"], "Café ☕ 🚀");
+    }
+
+    #[rstest]
+    fn test_console_prefix_only_on_console_lines() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let writer = SlowWriter::new(Duration::ZERO);
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                console_writer: Some(LogWriter::new(writer.clone())),
+                console_prefix: Some(String::from("[HEDGE] ")),
+                ..Default::default()
+            },
         );
+        log_and_wait(&mut logger, "This is a test.");
 
-        let mut log_contents = String::new();
+        let log_contents = std::fs::read_to_string(temp_dir.path().join("test.log")).unwrap();
+        assert!(writer.lines()[0].starts_with("[HEDGE] "));
+        assert!(log_contents.starts_with("1970-01-20T02:20:00.000000000Z [INF]"));
+    }
 
-        wait_until(
-            || {
-                if let Some(log_file) = std::fs::read_dir(&temp_dir)
-                    .expect("Failed to read directory")
-                    .filter_map(Result::ok)
-                    .filter(|entry| entry.path().is_file())
-                    .next()
-                {
-                    let log_file_path = log_file.path();
-                    log_contents = std::fs::read_to_string(&log_file_path)
-                        .expect("Error while reading log file");
-                    !log_contents.is_empty()
-                } else {
-                    false
-                }
+    #[rstest]
+    fn test_checkpoint_flushes_buffered_log_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let writer = SlowWriter::new(Duration::ZERO);
+        let mut logger = file_logger_with_config(
+            temp_dir.path(),
+            LoggerConfig {
+                console_writer: Some(LogWriter::new(writer)),
+                flush_barrier: true,
+                flush_on_checkpoint: true,
+                ..Default::default()
             },
-            Duration::from_secs(2),
         );
+        let log_path = temp_dir.path().join("test.log");
+        let mut send = |message: &str| {
+            logger
+                .send(
+                    1_650_000_000_000_000,
+                    LogLevel::Info,
+                    LogColor::Normal,
+                    String::from("RiskEngine"),
+                    String::from(message),
+                )
+                .unwrap()
+        };
+        send("First.");
+        let token = send("Second.");
+        assert!(!logger.wait_flushed(token, Duration::from_millis(100)));
+        assert!(std::fs::read_to_string(&log_path).unwrap().is_empty());
+
+        logger.checkpoint().unwrap();
 
+        assert!(logger.wait_flushed(token, Duration::from_secs(2)));
         assert_eq!(
-        log_contents,
-        "{\"timestamp\":1650000000000000,\"level\":\"INFO\",\"color\":\"Normal\",\"component\":\"RiskEngine\",\"message\":\"This is a test.\"}\n"
-    );
+            std::fs::read_to_string(&log_path).unwrap(),
+            "1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: First.\n\
+             1970-01-20T02:20:00.000000000Z [INF] TRADER-001.RiskEngine: Second.\n\
+             # barrier\n"
+        );
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_subscribe_with_ttl_skips_stale_events() {
+        let mut logger = logger_with_config(LoggerConfig {
+            clock: LogClock::new(|| 1_650_010_000_000_000),
+            ..Default::default()
+        });
+        let ttl_rx = logger.subscribe_with_ttl(10, Duration::from_secs(5));
+        let rx = logger.subscribe(10);
+
+        for (timestamp, message) in [
+            (1_650_000_000_000_000, "Stale."),
+            (1_650_009_000_000_000, "Fresh."),
+        ] {
+            logger.info(
+                timestamp,
+                LogColor::Normal,
+                String::from("RiskEngine"),
+                String::from(message),
+            );
+        }
+        log_and_wait(&mut logger, "Last.");
+
+        let ttl_messages: Vec<String> = ttl_rx.try_iter().collect();
+        assert_eq!(ttl_messages.len(), 1);
+        assert!(ttl_messages[0].contains("Fresh."));
+        assert_eq!(rx.try_iter().count(), 3);
+        assert_eq!(logger.expired_count(), 2);
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_log_stream_discards_events_expired_when_received() {
+        let now = Arc::new(AtomicU64::new(1_650_000_000_000_000));
+        let clock_now = now.clone();
+        let mut logger = logger_with_config(LoggerConfig {
+            clock: LogClock::new(move || clock_now.load(Ordering::Relaxed)),
+            ..Default::default()
+        });
+        let stream = logger.subscribe_with_ttl(10, Duration::from_secs(5));
+
+        log_and_wait(&mut logger, "First.");
+        now.store(1_650_010_000_000_000, Ordering::Relaxed);
+
+        assert_eq!(stream.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(logger.expired_count(), 1);
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_log_stream_displaces_oldest_when_full() {
+        let mut logger = logger_with_config(LoggerConfig {
+            clock: LogClock::new(|| 1_650_000_000_000_000),
+            ..Default::default()
+        });
+        let stream = logger.subscribe_with_ttl(2, Duration::from_secs(5));
+
+        for i in 1..=3 {
+            log_and_wait(&mut logger, &format!("Event {i}."));
+        }
+        let messages: Vec<String> = stream.try_iter().collect();
+        log_and_wait(&mut logger, "Event 4.");
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("Event 2."));
+        assert!(messages[1].contains("Event 3."));
+        assert!(stream.try_recv().unwrap().contains("Event 4."));
+        assert_eq!(logger.expired_count(), 1);
+
+        logger.shutdown();
+        assert_eq!(stream.recv(), Err(RecvError));
+    }
+
+    #[rstest]
+    #[cfg(feature = "log-stream")]
+    fn test_log_stream_removed_once_dropped(mut logger: Logger) {
+        let stream = logger.subscribe_with_ttl(10, Duration::from_secs(5));
+        drop(stream);
+
+        log_and_wait(&mut logger, "This is a test.");
+
+        assert!(lock(&logger.state.subscribers).is_empty());
     }
 }
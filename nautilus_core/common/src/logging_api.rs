@@ -27,7 +27,7 @@ use nautilus_model::identifiers::trader_id::TraderId;
 
 use crate::{
     enums::{LogColor, LogLevel},
-    logging::Logger,
+    logging::{LogCountsSnapshot, Logger, LoggerConfig},
 };
 
 /// Provides a C compatible Foreign Function Interface (FFI) for an underlying [`Logger`].
@@ -92,6 +92,7 @@ pub unsafe extern "C" fn logger_new(
         optional_cstr_to_string(file_format_ptr),
         optional_bytes_to_json(component_levels_ptr),
         is_bypassed != 0,
+        LoggerConfig::default(),
     )))
 }
 
@@ -120,8 +121,19 @@ pub extern "C" fn logger_is_bypassed(logger: &Logger_API) -> u8 {
     logger.is_bypassed as u8
 }
 
+/// Returns the path of the log file currently being written as a C string pointer, which is
+/// empty if not logging to a file.
+#[no_mangle]
+pub extern "C" fn logger_get_current_file_path_cstr(logger: &Logger_API) -> *const c_char {
+    let path = logger.current_file_path().unwrap_or_default();
+    str_to_cstr(&path.to_string_lossy())
+}
+
 /// Create a new log event.
 ///
+/// An event sent once shutdown has begun is not logged, and is counted in the logger's
+/// rejected count.
+///
 /// # Safety
 ///
 /// - Assumes `component_ptr` is a valid C string pointer.
@@ -137,5 +149,42 @@ pub unsafe extern "C" fn logger_log(
 ) {
     let component = cstr_to_string(component_ptr);
     let message = cstr_to_string(message_ptr);
-    logger.send(timestamp_ns, level, color, component, message);
+    // Events rejected during shutdown are counted by the logger
+    let _ = logger.send(timestamp_ns, level, color, component, message);
+}
+
+/// Pauses writing to all sinks until [`logger_resume`] is called, holding new log events.
+#[no_mangle]
+pub extern "C" fn logger_pause(logger: &Logger_API) {
+    logger.pause();
+}
+
+/// Resumes writing to all sinks, first writing any log events held while paused.
+#[no_mangle]
+pub extern "C" fn logger_resume(logger: &Logger_API) {
+    logger.resume();
+}
+
+/// Sets whether log events are captured in memory for retrieval with [`logger_take_captured`].
+///
+/// The capture buffer is unbounded until taken, so capturing should only be enabled briefly.
+#[no_mangle]
+pub extern "C" fn logger_set_capturing(logger: &Logger_API, capturing: u8) {
+    logger.set_capturing(capturing != 0);
+}
+
+/// Returns the captured log events as a JSON array C string pointer, leaving the capture
+/// buffer empty.
+#[no_mangle]
+pub extern "C" fn logger_take_captured(logger: &Logger_API) -> *const c_char {
+    let captured = logger.take_captured();
+    let json = serde_json::to_string(&captured).expect("Error serializing captured log events");
+    str_to_cstr(&json)
+}
+
+/// Returns the counts of log events per level and dropped since the counts were last taken,
+/// resetting them to zero.
+#[no_mangle]
+pub extern "C" fn logger_take_and_reset_counts(logger: &Logger_API) -> LogCountsSnapshot {
+    logger.take_and_reset_counts()
 }
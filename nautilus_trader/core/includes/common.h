@@ -248,6 +248,37 @@ typedef struct Logger_API {
     struct Logger_t *_0;
 } Logger_API;
 
+/**
+ * Represents the counts of events logged per level, and dropped, over an interval ending when
+ * the counts were taken with [`Logger::take_and_reset_counts`].
+ */
+typedef struct LogCountsSnapshot {
+    /**
+     * The count of debug events logged.
+     */
+    uint64_t debug;
+    /**
+     * The count of info events logged.
+     */
+    uint64_t info;
+    /**
+     * The count of warning events logged.
+     */
+    uint64_t warning;
+    /**
+     * The count of error events logged.
+     */
+    uint64_t error;
+    /**
+     * The count of critical events logged.
+     */
+    uint64_t critical;
+    /**
+     * The count of events dropped rather than logged.
+     */
+    uint64_t dropped;
+} LogCountsSnapshot;
+
 /**
  * Represents a time event occurring at the event timestamp.
  */
@@ -440,6 +471,12 @@ UUID4_t logger_get_instance_id(const struct Logger_API *logger);
 
 uint8_t logger_is_bypassed(const struct Logger_API *logger);
 
+/**
+ * Returns the path of the log file currently being written as a C string pointer, which is
+ * empty if not logging to a file.
+ */
+const char *logger_get_current_file_path_cstr(const struct Logger_API *logger);
+
 /**
  * Create a new log event.
  *
@@ -455,6 +492,35 @@ void logger_log(struct Logger_API *logger,
                 const char *component_ptr,
                 const char *message_ptr);
 
+/**
+ * Pauses writing to all sinks until [`logger_resume`] is called, holding new log events.
+ */
+void logger_pause(const struct Logger_API *logger);
+
+/**
+ * Resumes writing to all sinks, first writing any log events held while paused.
+ */
+void logger_resume(const struct Logger_API *logger);
+
+/**
+ * Sets whether log events are captured in memory for retrieval with [`logger_take_captured`].
+ *
+ * The capture buffer is unbounded until taken, so capturing should only be enabled briefly.
+ */
+void logger_set_capturing(const struct Logger_API *logger, uint8_t capturing);
+
+/**
+ * Returns the captured log events as a JSON array C string pointer, leaving the capture
+ * buffer empty.
+ */
+const char *logger_take_captured(const struct Logger_API *logger);
+
+/**
+ * Returns the counts of log events per level and dropped since the counts were last taken,
+ * resetting them to zero.
+ */
+struct LogCountsSnapshot logger_take_and_reset_counts(const struct Logger_API *logger);
+
 struct TimeEventHandler_t dummy(struct TimeEventHandler_t v);
 
 /**